@@ -21,6 +21,8 @@
 
 [`LiveReload`]: https://docs.rs/tower-livereload/latest/tower_livereload/struct.LiveReload.html
 [`Reloader`]: https://docs.rs/tower-livereload/latest/tower_livereload/struct.Reloader.html
+[`Reloader::send`]: https://docs.rs/tower-livereload/latest/tower_livereload/struct.Reloader.html#method.send
+[`event::ReloadEvent`]: https://docs.rs/tower-livereload/latest/tower_livereload/event/enum.ReloadEvent.html
 
 ## License
 
@@ -1,26 +1,956 @@
 (() => {
-  const inputs = document.currentScript.dataset;
+  const script = document.currentScript;
+  const inputs = script.dataset;
+  // Storage is not available everywhere, e.g. in sandboxed iframes, in which
+  // case nothing is kept across reloads.
+  const storage = {
+    get: (key) => {
+      try {
+        return sessionStorage.getItem(key);
+      } catch {
+        return null;
+      }
+    },
+    set: (key, value) => {
+      try {
+        sessionStorage.setItem(key, value);
+      } catch {}
+    },
+    remove: (key) => {
+      try {
+        sessionStorage.removeItem(key);
+      } catch {}
+    },
+  };
+  const reloadedKey = "tower-livereload:reloaded";
+  // Identify this page across reloads, so that the server can tell which pages
+  // have acknowledged a reload. Stored tokens are only reused after reloads
+  // triggered by us, as duplicated tabs and popups start with a copy of the
+  // storage of their opener.
+  const clientKey = "tower-livereload:client";
+  const client =
+    (storage.get(reloadedKey) !== null && storage.get(clientKey)) ||
+    `${Date.now().toString(36)}-${Math.random().toString(36).slice(2)}`;
+  storage.set(clientKey, client);
+  // Generation of the server when this page was rendered, which is advanced
+  // by every received event.
+  let lastEventId = inputs.lastEventId;
+  // Keep the original console methods, so that server logs printed by us are
+  // not reported back to the server.
+  const nativeConsole = { ...console };
+
+  const matches = (url, path, base = location.href) => {
+    try {
+      return new URL(url, base).pathname === path;
+    } catch {
+      return false;
+    }
+  };
+
+  const bust = (url) => {
+    const busted = new URL(url, location.href);
+    busted.searchParams.set("livereload", Date.now());
+    return busted.href;
+  };
+
+  const unbust = (url) => {
+    try {
+      const original = new URL(url, location.href);
+      original.searchParams.delete("livereload");
+      return original.href;
+    } catch {
+      return url;
+    }
+  };
+
+  const updateImports = (sheet, path) => {
+    let rules;
+    try {
+      rules = [...sheet.cssRules];
+    } catch {
+      // Rules of cross-origin stylesheets are not accessible.
+      return 0;
+    }
+
+    let updated = 0;
+    rules.forEach((rule, index) => {
+      if (!(rule instanceof CSSImportRule)) {
+        return;
+      }
+
+      const base = sheet.href ?? location.href;
+      if (matches(rule.href, path, base)) {
+        const href = bust(new URL(rule.href, base));
+        sheet.insertRule(rule.cssText.replace(rule.href, href), index);
+        sheet.deleteRule(index + 1);
+        updated += 1;
+      } else if (rule.styleSheet) {
+        updated += updateImports(rule.styleSheet, path);
+      }
+    });
+
+    return updated;
+  };
+
+  const updateStylesheet = (path) => {
+    const links = [...document.querySelectorAll("link[rel~=stylesheet]")];
+    const matching = links.filter((link) => matches(link.href, path));
+    for (const link of matching) {
+      const clone = link.cloneNode();
+      clone.href = bust(link.href);
+      clone.addEventListener("load", () => link.remove());
+      // Keep the previous version if the new one fails to load.
+      clone.addEventListener("error", () => clone.remove());
+      link.after(clone);
+    }
+
+    const sheets = [...document.styleSheets];
+    const imports = sheets
+      .filter((sheet) => !matching.includes(sheet.ownerNode))
+      .reduce((updated, sheet) => updated + updateImports(sheet, path), 0);
+
+    return matching.length + imports > 0;
+  };
+
+  const bustSrcset = (srcset, path) => {
+    let updated = false;
+    const candidates = srcset.split(",").map((candidate) => {
+      const [url, ...descriptors] = candidate.trim().split(/\s+/);
+      if (!matches(url, path)) {
+        return candidate;
+      }
+
+      updated = true;
+      return [bust(url), ...descriptors].join(" ");
+    });
+
+    return updated ? candidates.join(", ") : null;
+  };
+
+  const bustCssUrls = (value, path, base = location.href) => {
+    let updated = false;
+    const busted = value.replace(
+      /url\(\s*(['"]?)(.*?)\1\s*\)/g,
+      (match, _quote, url) => {
+        if (url.startsWith("data:") || !matches(url, path, base)) {
+          return match;
+        }
+
+        updated = true;
+        return `url("${bust(new URL(url, base))}")`;
+      },
+    );
+
+    return updated ? busted : null;
+  };
+
+  const updateBackgrounds = (rules, path, base) => {
+    let updated = 0;
+    for (const rule of rules) {
+      const value = rule.style?.backgroundImage;
+      const busted = value && bustCssUrls(value, path, base);
+      if (busted) {
+        rule.style.backgroundImage = busted;
+        updated += 1;
+      }
+
+      try {
+        updated += updateBackgrounds(rule.cssRules ?? [], path, base);
+        if (rule.styleSheet) {
+          const sheet = rule.styleSheet;
+          updated += updateBackgrounds(sheet.cssRules, path, sheet.href);
+        }
+      } catch {
+        // Rules of cross-origin stylesheets are not accessible.
+      }
+    }
+
+    return updated;
+  };
+
+  const updateAsset = (path) => {
+    const updated = new Set();
+    const selector = "img, source, video, audio, input[type=image]";
+    for (const element of document.querySelectorAll(selector)) {
+      for (const attribute of ["src", "poster"]) {
+        const value = element.getAttribute(attribute);
+        if (value && matches(value, path)) {
+          element.setAttribute(attribute, bust(value));
+          updated.add(element);
+        }
+      }
+
+      const srcset = element.getAttribute("srcset");
+      const busted = srcset && bustSrcset(srcset, path);
+      if (busted) {
+        element.setAttribute("srcset", busted);
+        updated.add(element);
+      }
+    }
+
+    // Media elements only pick up changed sources once they are reloaded.
+    for (const element of updated) {
+      const parent = element.parentElement;
+      if (element.localName === "source" && parent instanceof HTMLMediaElement) {
+        parent.load();
+      }
+    }
+
+    for (const link of document.querySelectorAll("link[rel~=icon]")) {
+      if (matches(link.href, path)) {
+        // Browsers only pick up favicons that are newly inserted.
+        const clone = link.cloneNode();
+        clone.href = bust(link.href);
+        link.replaceWith(clone);
+        updated.add(clone);
+      }
+    }
+
+    for (const element of document.querySelectorAll("[style*=url]")) {
+      const busted = bustCssUrls(element.style.backgroundImage, path);
+      if (busted) {
+        element.style.backgroundImage = busted;
+        updated.add(element);
+      }
+    }
+
+    let rules = 0;
+    for (const sheet of document.styleSheets) {
+      try {
+        const base = sheet.href ?? location.href;
+        rules += updateBackgrounds(sheet.cssRules, path, base);
+      } catch {
+        // Rules of cross-origin stylesheets are not accessible.
+      }
+    }
+
+    return updated.size + rules > 0;
+  };
+
+  // Callbacks registered by modules that accept hot replacement, by path.
+  const modules = new Map();
+  const moduleData = new Map();
+
+  window.towerLiveReload = {
+    hot(url) {
+      const path = new URL(url, location.href).pathname;
+      const callbacks = { accept: [], dispose: [] };
+      modules.set(path, callbacks);
+      return {
+        data: moduleData.get(path) ?? {},
+        accept: (callback = () => {}) => callbacks.accept.push(callback),
+        dispose: (callback) => callbacks.dispose.push(callback),
+      };
+    },
+  };
+
+  const updateModule = async (path) => {
+    const callbacks = modules.get(path);
+    if (!callbacks || callbacks.accept.length === 0) {
+      return false;
+    }
+
+    const data = {};
+    for (const dispose of callbacks.dispose) {
+      await dispose(data);
+    }
+    moduleData.set(path, data);
+    modules.delete(path);
+
+    const module = await import(bust(path));
+    for (const accept of callbacks.accept) {
+      await accept(module);
+    }
+
+    return true;
+  };
+
+  const notice = document.createElement("div");
+  notice.textContent = "Live reload paused";
+  notice.style.cssText = [
+    "position: fixed",
+    "right: 1em",
+    "bottom: 1em",
+    "z-index: 2147483647",
+    "padding: 0.5em 1em",
+    "border-radius: 0.25em",
+    "background: #333",
+    "color: #fff",
+    "font: 14px sans-serif",
+  ].join(";");
+
+  let paused = false;
+  const setPaused = (value) => {
+    paused = value;
+    if (paused) {
+      document.body.append(notice);
+    } else {
+      notice.remove();
+    }
+  };
+
+  const overlay = document.createElement("tower-livereload-overlay");
+  const shadow = overlay.attachShadow({ mode: "open" });
+  const overlayStyle = `
+    .spinner {
+      position: fixed;
+      top: 1em;
+      right: 1em;
+      z-index: 2147483647;
+      padding: 0.5em 1em;
+      border-radius: 0.25em;
+      background: #333;
+      color: #fff;
+      font: 14px sans-serif;
+    }
+    .spinner::before {
+      content: "";
+      display: inline-block;
+      width: 0.8em;
+      height: 0.8em;
+      margin-right: 0.5em;
+      border: 2px solid currentColor;
+      border-right-color: transparent;
+      border-radius: 50%;
+      vertical-align: -0.15em;
+      animation: spin 0.8s linear infinite;
+    }
+    @keyframes spin {
+      to {
+        transform: rotate(360deg);
+      }
+    }
+    .error {
+      position: fixed;
+      inset: 0;
+      z-index: 2147483647;
+      overflow: auto;
+      padding: 2em;
+      background: rgba(0, 0, 0, 0.9);
+      color: #fff;
+      font: 14px sans-serif;
+    }
+    .error pre {
+      font: 13px monospace;
+      white-space: pre-wrap;
+      color: #ff8a80;
+    }
+    .error button {
+      position: absolute;
+      top: 1em;
+      right: 1em;
+      border: none;
+      background: none;
+      color: inherit;
+      font-size: 2em;
+      cursor: pointer;
+    }
+  `;
+
+  const showBuild = (build) => {
+    const style = document.createElement("style");
+    style.textContent = overlayStyle;
+    const panel = document.createElement("div");
+
+    if (build.status === "started") {
+      panel.className = "spinner";
+      panel.textContent = "Rebuilding…";
+    } else if (build.status === "failed") {
+      const dismiss = document.createElement("button");
+      dismiss.textContent = "×";
+      dismiss.title = "Dismiss";
+      dismiss.addEventListener("click", () => overlay.remove());
+      const title = document.createElement("h2");
+      title.textContent = "Build failed";
+      const messages = (build.diagnostics ?? [build]).map((diagnostic) => {
+        const { span, rendered } = diagnostic;
+        const location = span
+          ? `${span.file}:${span.line_start}:${span.column_start}: `
+          : "";
+        // Rendered compiler output already includes the location, but may
+        // contain ANSI color codes.
+        const message = document.createElement("pre");
+        message.textContent = rendered
+          ? rendered.replace(/\x1b\[[0-9;]*m/g, "")
+          : location + diagnostic.message;
+        return message;
+      });
+
+      panel.className = "error";
+      panel.append(dismiss, title, ...messages);
+    } else {
+      overlay.remove();
+      return;
+    }
+
+    shadow.replaceChildren(style, panel);
+    document.body.append(overlay);
+  };
+
+  const report = (fields) => {
+    const body = new URLSearchParams({ url: location.href });
+    for (const [key, value] of Object.entries(fields)) {
+      if (value !== undefined) {
+        body.set(key, value);
+      }
+    }
+    fetch(inputs.log, { method: "POST", body, keepalive: true }).catch(() => {});
+  };
+
+  const format = (value) => {
+    if (typeof value === "string") {
+      return value;
+    } else if (value instanceof Error) {
+      return value.stack ?? String(value);
+    }
+    try {
+      return JSON.stringify(value) ?? String(value);
+    } catch {
+      return String(value);
+    }
+  };
+
+  if (inputs.log) {
+    addEventListener(
+      "error",
+      (event) => {
+        if (event instanceof ErrorEvent) {
+          report({
+            kind: "error",
+            level: "error",
+            message: event.message,
+            source: `${event.filename}:${event.lineno}:${event.colno}`,
+            stack: event.error?.stack,
+          });
+        } else if (event.target instanceof Element) {
+          // Failed resources do not bubble, hence the capturing listener.
+          const element = event.target;
+          report({
+            kind: "resource",
+            level: "error",
+            message: `Failed to load <${element.localName}>`,
+            source: element.currentSrc || element.src || element.href,
+          });
+        }
+      },
+      true,
+    );
+    addEventListener("unhandledrejection", (event) => {
+      report({
+        kind: "rejection",
+        level: "error",
+        message: `Unhandled rejection: ${format(event.reason)}`,
+        stack: event.reason?.stack,
+      });
+    });
+  }
+
+  if (inputs.log && inputs.console !== undefined) {
+    for (const level of ["trace", "debug", "log", "info", "warn", "error"]) {
+      console[level] = (...args) => {
+        nativeConsole[level](...args);
+        report({ kind: "console", level, message: args.map(format).join(" ") });
+      };
+    }
+  }
+
+  // Elements added by us, which are kept when morphing the document.
+  const ownElements = [script, notice, overlay];
+
+  const compatible = (from, to) =>
+    from.nodeType === to.nodeType &&
+    from.nodeName === to.nodeName &&
+    (from.id ?? "") === (to.id ?? "");
+
+  const morphAttributes = (from, to) => {
+    for (const { name } of [...from.attributes]) {
+      // Keep <details> elements open or closed, as they were left by the user.
+      const toggled = from.localName === "details" && name === "open";
+      if (!to.hasAttribute(name) && !toggled) {
+        from.removeAttribute(name);
+      }
+    }
+    for (const { name, value } of to.attributes) {
+      const toggled = from.localName === "details" && name === "open";
+      // Keep URLs that were updated by us, instead of loading stale versions.
+      const busted =
+        (name === "href" || name === "src") &&
+        from.hasAttribute(name) &&
+        unbust(from.getAttribute(name)) === unbust(value);
+      if (from.getAttribute(name) !== value && !toggled && !busted) {
+        from.setAttribute(name, value);
+      }
+    }
+  };
+
+  const morphChildren = (from, to) => {
+    const children = [...from.childNodes].filter(
+      (child) => !ownElements.includes(child),
+    );
+    let index = 0;
+    for (const target of to.childNodes) {
+      const current = children[index];
+      const equal = children
+        .slice(index)
+        .findIndex((child) => child.isEqualNode(target));
+      if (equal > 0) {
+        // Nodes before an unchanged node have been removed.
+        children.splice(index, equal).forEach((child) => child.remove());
+      }
+
+      if (equal >= 0 || (current && compatible(current, target))) {
+        morphNode(children[index], target);
+        index += 1;
+        continue;
+      }
+
+      const moved = target.id
+        ? children.slice(index).find((child) => compatible(child, target))
+        : undefined;
+      const node = moved ?? document.importNode(target, true);
+      if (moved) {
+        children.splice(children.indexOf(moved), 1);
+        morphNode(moved, target);
+      }
+      from.insertBefore(node, current ?? null);
+    }
+
+    children.slice(index).forEach((child) => child.remove());
+  };
+
+  const morphNode = (from, to) => {
+    if (from.isEqualNode(to)) {
+      return;
+    } else if (from.nodeType === Node.ELEMENT_NODE) {
+      morphAttributes(from, to);
+      morphChildren(from, to);
+    } else {
+      from.nodeValue = to.nodeValue;
+    }
+  };
+
+  const headScripts = (doc) =>
+    [...doc.head.querySelectorAll("script")]
+      .filter((element) => element !== script)
+      .map((element) => element.outerHTML)
+      .join("\n");
+
+  const morph = async () => {
+    const response = await fetch(location.href, {
+      headers: { [inputs.morph]: "1" },
+      cache: "no-store",
+    });
+    if (!response.ok) {
+      return false;
+    }
+
+    const html = await response.text();
+    const next = new DOMParser().parseFromString(html, "text/html");
+    if (headScripts(next) !== headScripts(document)) {
+      return false;
+    }
+
+    morphAttributes(document.documentElement, next.documentElement);
+    morphNode(document.head, next.head);
+    morphNode(document.body, next.body);
+    return true;
+  };
+
+  const scrollKey = `tower-livereload:scroll:${location.href}`;
+
+  const saveScroll = () => {
+    const elements = {};
+    for (const element of document.querySelectorAll("[id]")) {
+      if (element.scrollTop || element.scrollLeft) {
+        elements[element.id] = [element.scrollLeft, element.scrollTop];
+      }
+    }
+
+    const scroll = { window: [scrollX, scrollY], elements };
+    storage.set(scrollKey, JSON.stringify(scroll));
+  };
+
+  const restoreScroll = () => {
+    const scroll = JSON.parse(storage.get(scrollKey));
+    storage.remove(scrollKey);
+    if (!scroll) {
+      return;
+    }
+
+    for (const [id, [left, top]] of Object.entries(scroll.elements)) {
+      document.getElementById(id)?.scrollTo(left, top);
+    }
+    scrollTo(...scroll.window);
+  };
+
+  const formsKey = `tower-livereload:forms:${location.href}`;
+
+  // Yield form fields along with keys that identify them across reloads.
+  function* formFields() {
+    const skipped = ["button", "file", "hidden", "image", "reset", "submit"];
+    if (inputs.preservePasswords === undefined) {
+      skipped.push("password");
+    }
+
+    const seen = {};
+    for (const field of document.querySelectorAll("input, textarea, select")) {
+      const name = field.name || field.id;
+      if (!name || skipped.includes(field.type)) {
+        continue;
+      }
+
+      seen[name] = (seen[name] ?? -1) + 1;
+      yield [`${name}#${seen[name]}`, field];
+    }
+  }
+
+  const saveForms = () => {
+    const values = {};
+    for (const [key, field] of formFields()) {
+      if (field.type === "checkbox" || field.type === "radio") {
+        values[key] = field.checked;
+      } else if (field.type === "select-multiple") {
+        values[key] = [...field.selectedOptions].map((option) => option.value);
+      } else {
+        values[key] = field.value;
+      }
+    }
+
+    storage.set(formsKey, JSON.stringify(values));
+  };
+
+  const restoreForms = () => {
+    const values = JSON.parse(storage.get(formsKey));
+    storage.remove(formsKey);
+    if (!values) {
+      return;
+    }
+
+    for (const [key, field] of formFields()) {
+      const value = values[key];
+      if (value === undefined) {
+        continue;
+      } else if (field.type === "checkbox" || field.type === "radio") {
+        field.checked = value;
+      } else if (field.type === "select-multiple") {
+        for (const option of field.options) {
+          option.selected = value.includes(option.value);
+        }
+      } else {
+        field.value = value;
+      }
+
+      field.dispatchEvent(new Event("input", { bubbles: true }));
+      field.dispatchEvent(new Event("change", { bubbles: true }));
+    }
+  };
+
+  // Dispatch a lifecycle event, returning false if it has been cancelled.
+  const emit = (name, detail) =>
+    dispatchEvent(
+      new CustomEvent(`tower-livereload:${name}`, { detail, cancelable: true }),
+    );
+
+  // Reload that has been deferred until the page becomes visible again.
+  let stale = null;
+  const whenVisible = (hard, callback) => {
+    const hidden = document.visibilityState === "hidden";
+    if (inputs.deferHidden === undefined || !hidden) {
+      callback();
+    } else if (!stale?.hard) {
+      // Hard reloads take precedence, as they also apply all other updates.
+      stale = { hard, callback };
+    }
+  };
+
+  document.addEventListener("visibilitychange", () => {
+    if (document.visibilityState === "visible" && stale) {
+      const { callback } = stale;
+      stale = null;
+      callback();
+    }
+  });
+
+  const acknowledge = (id) => {
+    const url = new URL(inputs.acknowledge, location.href);
+    url.searchParams.set("id", id);
+    url.searchParams.set("client", client);
+    fetch(url, { method: "POST" }).catch(() => {});
+  };
 
   addEventListener("pageshow", () => {
-    const source = new EventSource(inputs.eventStream);
-    source.addEventListener("reload", () => {
-      source.close();
+    if (inputs.preserveForms !== undefined) {
+      restoreForms();
+    }
+    if (inputs.preserveScroll !== undefined) {
+      restoreScroll();
+    }
+
+    const url = new URL(inputs.eventStream, location.href);
+    url.searchParams.set("path", location.pathname);
+    url.searchParams.set("url", location.href);
+    url.searchParams.set("client", client);
+
+    const selector = "meta[name=tower-livereload-channels]";
+    const markers = [...document.querySelectorAll(selector)];
+    const channels = [inputs.channels, ...markers.map((meta) => meta.content)]
+      .flatMap((list) => list.split(/\s+/))
+      .filter((channel) => channel);
+    if (channels.length > 0) {
+      url.searchParams.set("channels", channels.join(","));
+    }
+
+    // Pick up events that were sent after this page was rendered.
+    url.searchParams.set("last-event-id", lastEventId);
+
+    // Acknowledge that a reload triggered by us has finished.
+    const reloaded = storage.get(reloadedKey);
+    if (reloaded !== null) {
+      storage.remove(reloadedKey);
+      acknowledge(reloaded);
+    }
+
+    // Events are dispatched on this target, regardless of whether this page
+    // is connected to the server itself or shares the connection of another.
+    const stream = new EventTarget();
+    let source = null;
+
+    const reload = (event) => {
+      source?.close();
+      storage.set(reloadedKey, event.lastEventId);
+      if (inputs.preserveForms !== undefined) {
+        saveForms();
+      }
+      if (inputs.preserveScroll !== undefined) {
+        saveScroll();
+      }
       window.location.reload();
+    };
+
+    // Morph reloads are applied one after another, as they are asynchronous.
+    let morphing = Promise.resolve();
+    const update = (event) => whenVisible(false, () => {
+      const strategy = inputs.morph === undefined ? "reload" : "morph";
+      if (!emit("before-reload", { reason: event.type, strategy })) {
+        return;
+      }
+
+      if (inputs.morph === undefined) {
+        reload(event);
+        return;
+      }
+
+      morphing = morphing
+        .then(morph)
+        .catch(() => false)
+        .then((morphed) => {
+          if (morphed) {
+            acknowledge(event.lastEventId);
+          } else {
+            reload(event);
+          }
+        });
     });
 
-    const onerror = () => {
-      source.removeEventListener("error", onerror);
-      source.addEventListener("init", () => {
-        source.close();
-        window.location.reload();
+    // Every event from the server is announced to the page first, which can
+    // cancel its built-in handling.
+    const types = [];
+    const on = (type, handler) => {
+      types.push(type);
+      stream.addEventListener(type, (event) => {
+        const detail = { type, data: event.data, id: event.lastEventId };
+        if (emit("message", detail)) {
+          handler(event);
+        }
       });
     };
 
-    source.addEventListener("error", onerror);
+    // Events received over a shared connection list the pages they are
+    // addressed to, or `*` if they are addressed to all pages.
+    const addressed = (message) =>
+      !message.to || message.to.includes("*") || message.to.includes(client);
+
+    let connected = false;
+    const connect = (publish) => {
+      source = new EventSource(url);
+      for (const type of types) {
+        source.addEventListener(type, (event) => {
+          lastEventId = event.lastEventId;
+          let message = { type, data: event.data, lastEventId };
+          if (publish) {
+            const [to, ...data] = event.data.split("\n");
+            message = { ...message, data: data.join("\n"), to: to.split(" ") };
+            publish(message);
+          }
+          if (addressed(message)) {
+            stream.dispatchEvent(new MessageEvent(type, message));
+          }
+        });
+      }
+
+      source.addEventListener("error", () => {
+        if (!connected) {
+          return;
+        }
+
+        connected = false;
+        const reconnecting = source.readyState === EventSource.CONNECTING;
+        if (!emit("disconnected", { reconnecting })) {
+          source.close();
+        }
+      });
+    };
 
-    addEventListener("pagehide", () => {
-      source.removeEventListener("error", onerror);
-      source.close();
+    on("init", (event) => {
+      const { instance, paused } = JSON.parse(event.data);
+      connected = true;
+      const restarted = instance !== inputs.instance;
+      if (!emit("connected", { instance, paused, restarted })) {
+        return;
+      }
+
+      // Only reload when the server has been restarted, as opposed to
+      // reconnects caused by network errors or sleeping devices.
+      if (!restarted) {
+        setPaused(paused);
+      } else {
+        whenVisible(true, () => {
+          const detail = { reason: "restart", strategy: "reload" };
+          if (emit("before-reload", detail)) {
+            reload(event);
+          }
+        });
+      }
+    });
+    on("paused", () => setPaused(true));
+    on("resumed", () => setPaused(false));
+    on("reload", update);
+    on("stylesheet", (event) => {
+      updateStylesheet(event.data) || update(event);
+    });
+    on("asset", (event) => {
+      updateAsset(event.data) || update(event);
+    });
+    on("module", (event) => {
+      updateModule(event.data)
+        .catch((error) => {
+          nativeConsole.error(error);
+          return false;
+        })
+        .then((updated) => updated || update(event));
+    });
+    on("build", (event) => {
+      const build = JSON.parse(event.data);
+      showBuild(build);
+      if (build.status === "succeeded" && !paused) {
+        update(event);
+      }
+    });
+    on("log", (event) => {
+      const { level, target, message } = JSON.parse(event.data);
+      const print =
+        { error: nativeConsole.error, warn: nativeConsole.warn }[level] ??
+        nativeConsole.log;
+      print(`[server] ${level.toUpperCase()} ${target}: ${message}`);
+    });
+    on("custom", (event) => {
+      const { name, data } = JSON.parse(event.data);
+      // Custom events have their own namespace, so that they cannot be
+      // mistaken for the built-in events.
+      emit(`custom:${name}`, data);
     });
+
+    const shared =
+      inputs.share !== undefined &&
+      "locks" in navigator &&
+      "BroadcastChannel" in window;
+    if (!shared) {
+      connect();
+      addEventListener("pagehide", () => source.close(), { once: true });
+      return;
+    }
+
+    // Elect a single page to hold the connection, which forwards all events
+    // to the other pages. Once it goes away, the lock passes to another page.
+    const name = `tower-livereload:${new URL(url).pathname}`;
+    const channel = new BroadcastChannel(name);
+
+    // The other pages register with the server, so that it can address events
+    // to them as well.
+    let following = null;
+    const share = (stream, leave = false) => {
+      const request = new URL(inputs.share, location.href);
+      for (const [key, value] of url.searchParams) {
+        request.searchParams.set(key, value);
+      }
+      request.searchParams.delete("last-event-id");
+      request.searchParams.set("stream", stream);
+      if (leave) {
+        request.searchParams.set("leave", "");
+        navigator.sendBeacon(request);
+      } else {
+        fetch(request, { method: "POST" }).catch(() => {});
+      }
+    };
+
+    let init = null;
+    channel.addEventListener("message", ({ data: message }) => {
+      if (message === "hello") {
+        // Let newly opened pages know about the current state.
+        if (source && init) {
+          channel.postMessage({ ...init, replayed: true });
+        }
+        return;
+      }
+
+      // The state of the connection may be older than this page.
+      if (!message.replayed) {
+        lastEventId = message.lastEventId;
+      }
+      // Connections send their id once connected, including reconnects.
+      if (message.type === "init" && !source) {
+        following = JSON.parse(message.data).stream;
+        share(following);
+      }
+      if (addressed(message)) {
+        stream.dispatchEvent(new MessageEvent(message.type, message));
+      }
+    });
+
+    const abort = new AbortController();
+    const lock = navigator.locks.request(name, { signal: abort.signal }, () => {
+      if (following !== null) {
+        share(following, true);
+        following = null;
+      }
+
+      // Resume from the last event seen by this page, whether it was received
+      // from the previous connection or was current when the page was loaded.
+      url.searchParams.set("last-event-id", lastEventId);
+      url.searchParams.set("shared", "");
+      connect((message) => {
+        init = message.type === "init" ? message : init;
+        channel.postMessage(message);
+      });
+
+      return new Promise((resolve) => {
+        addEventListener("pagehide", resolve, { once: true });
+      });
+    });
+    lock.catch(() => {});
+    channel.postMessage("hello");
+
+    addEventListener(
+      "pagehide",
+      () => {
+        abort.abort();
+        source?.close();
+        channel.close();
+        if (following !== null) {
+          share(following, true);
+        }
+      },
+      { once: true },
+    );
   });
 })();
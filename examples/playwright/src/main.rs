@@ -1,14 +1,65 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use axum::{
-    response::Html,
-    routing::{get, post},
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post, put},
     Router,
 };
-use tower_livereload::LiveReloadLayer;
+use tower_livereload::{LiveReloadLayer, ReloadStrategy, Reloader};
+
+/// Files served by the test server, keyed by path, which tests can replace
+/// using `PUT /files/<path>` and remove using `DELETE /files/<path>`.
+type Files = Arc<Mutex<HashMap<String, (String, String)>>>;
+
+/// Messages reported by browsers, which tests can read using
+/// `GET /browser-logs`.
+type Logs = Arc<Mutex<Vec<String>>>;
+
+/// Pages below these paths are injected with non-default options.
+const VARIANTS: &[&str] = &["/morph/", "/scroll/", "/forms/", "/defer/", "/shared/"];
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let livereload = LiveReloadLayer::new();
-    let reloader = livereload.reloader();
+    let reloader = Reloader::new();
+    let logs = Logs::default();
+    let default = LiveReloadLayer::with_reloader(reloader.clone())
+        .trigger(true)
+        .on_browser_log({
+            let logs = logs.clone();
+            move |log| {
+                let line = format!("{:?} {:?}: {}", log.kind(), log.level(), log.message());
+                logs.lock().unwrap().push(line);
+            }
+        })
+        .capture_console(true)
+        .request_predicate(|request: &Request| {
+            !VARIANTS
+                .iter()
+                .any(|variant| request.uri().path().starts_with(variant))
+        });
+    let morph = LiveReloadLayer::with_reloader(reloader.clone())
+        .reload_strategy(ReloadStrategy::Morph)
+        .request_predicate(under("/morph/"));
+    let scroll = LiveReloadLayer::with_reloader(reloader.clone())
+        .preserve_scroll(true)
+        .request_predicate(under("/scroll/"));
+    let forms = LiveReloadLayer::with_reloader(reloader.clone())
+        .preserve_forms(true)
+        .request_predicate(under("/forms/"));
+    let defer = LiveReloadLayer::with_reloader(reloader.clone())
+        .defer_hidden_reloads(true)
+        .request_predicate(under("/defer/"));
+    let shared = LiveReloadLayer::with_reloader(reloader.clone())
+        .share_connection(true)
+        .request_predicate(under("/shared/"));
+
+    let waiter = reloader.clone();
     let app = Router::new()
         .route("/", get(|| async { Html("<h1>Playwright!</h1>") }))
         .route(
@@ -17,10 +68,75 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 reloader.reload();
             }),
         )
-        .layer(livereload);
+        .route(
+            "/reload-and-wait",
+            post(|| async move {
+                let ack = waiter.reload_and_wait(Duration::from_secs(2)).await;
+                format!(
+                    "acknowledged={} timed_out={}",
+                    ack.acknowledged, ack.timed_out
+                )
+            }),
+        )
+        .route(
+            "/browser-logs",
+            get(|| async move { logs.lock().unwrap().join("\n") }),
+        )
+        .route("/files/{*path}", put(store).delete(remove))
+        .fallback(get(serve))
+        .with_state(Files::default())
+        .layer(default)
+        .layer(morph)
+        .layer(scroll)
+        .layer(forms)
+        .layer(defer)
+        .layer(shared);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3030").await?;
     axum::serve(listener, app).await?;
 
     Ok(())
 }
+
+fn under(prefix: &'static str) -> impl Fn(&Request) -> bool + Copy {
+    move |request: &Request| request.uri().path().starts_with(prefix)
+}
+
+async fn store(
+    State(files): State<Files>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html; charset=utf-8")
+        .to_owned();
+    files
+        .lock()
+        .unwrap()
+        .insert(format!("/{}", path), (content_type, body));
+
+    StatusCode::NO_CONTENT
+}
+
+async fn remove(State(files): State<Files>, Path(path): Path<String>) -> StatusCode {
+    files.lock().unwrap().remove(&format!("/{}", path));
+
+    StatusCode::NO_CONTENT
+}
+
+async fn serve(State(files): State<Files>, uri: Uri) -> Response {
+    match files.lock().unwrap().get(uri.path()) {
+        Some((content_type, body)) => (
+            [
+                (header::CONTENT_TYPE, content_type.clone()),
+                (header::CACHE_CONTROL, "no-store".to_owned()),
+            ],
+            body.clone(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,66 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+test("acknowledged reload", async ({ context, request }) => {
+  await serve(request, "/acknowledge/", "<h1>Acknowledge</h1>");
+  const first = await context.newPage();
+  const second = await context.newPage();
+  await open(first, "/acknowledge/");
+  await open(second, "/acknowledge/");
+
+  // Each page acknowledges the reload once it has loaded again.
+  const response = await request.post("/reload-and-wait");
+  expect(await response.text()).toMatch(/^acknowledged=2 /);
+});
+
+test("acknowledged reload of popups", async ({ context, request }) => {
+  await serve(request, "/acknowledge/popup/", "<h1>Popup</h1>");
+  const opener = await context.newPage();
+  await open(opener, "/acknowledge/popup/");
+
+  // Popups start with a copy of the storage of their opener, but are still
+  // counted as pages of their own.
+  await context.addInitScript(() => {
+    addEventListener("tower-livereload:connected", () => {
+      console.log("connected");
+    });
+  });
+  const connected = context.waitForEvent("console", {
+    predicate: (message) =>
+      message.text() === "connected" && message.page() !== opener,
+  });
+  await opener.evaluate(() => {
+    window.open(location.href);
+  });
+  await connected;
+
+  const response = await request.post("/reload-and-wait");
+  expect(await response.text()).toMatch(/^acknowledged=2 /);
+});
+
+test("reload without storage", async ({ page, request }) => {
+  await serve(request, "/acknowledge/storage/", "<h1>Storage</h1>");
+  // Browsers deny access to storage e.g. in sandboxed frames.
+  await page.addInitScript(() => {
+    Object.defineProperty(window, "sessionStorage", {
+      get: () => {
+        throw new DOMException("Access is denied", "SecurityError");
+      },
+    });
+  });
+  await open(page, "/acknowledge/storage/");
+  await mark(page);
+
+  const reload = page.waitForEvent("load");
+  await send(request, "reload");
+  await reload;
+  expect(await isMarked(page)).toBe(false);
+});
+
+test("acknowledgements from other origins", async ({ request }) => {
+  const response = await request.post(
+    "/_tower-livereload/acknowledge?id=1&client=fake",
+    { headers: { origin: "https://example.com" } },
+  );
+  expect(response.status()).toBe(403);
+});
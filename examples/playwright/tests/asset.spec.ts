@@ -0,0 +1,36 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+const logo =
+  '<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"></svg>';
+
+test("asset update", async ({ page, request }) => {
+  await serve(request, "/assets/logo.svg", logo, "image/svg+xml");
+  await serve(
+    request,
+    "/assets/",
+    `<img src="/assets/logo.svg" srcset="/assets/logo.svg 2x">
+     <div style="background-image: url('/assets/logo.svg')">Logo</div>`,
+  );
+  await open(page, "/assets/");
+  await mark(page);
+
+  await send(request, "asset /assets/logo.svg");
+
+  // All references to the asset are updated without reloading the page.
+  const image = page.locator("img");
+  await expect(image).toHaveAttribute("src", /livereload=/);
+  await expect(image).toHaveAttribute("srcset", /livereload=\d+ 2x$/);
+  await expect(page.locator("div")).toHaveAttribute("style", /livereload=/);
+  expect(await isMarked(page)).toBe(true);
+});
+
+test("asset update fallback", async ({ page, request }) => {
+  await serve(request, "/assets/unused/", "<p>No images here</p>");
+  await open(page, "/assets/unused/");
+
+  // Assets that are not referenced by the page fall back to a full reload.
+  const reload = page.waitForRequest("/assets/unused/");
+  await send(request, "asset /assets/other.svg");
+  await reload;
+});
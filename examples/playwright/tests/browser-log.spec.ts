@@ -0,0 +1,37 @@
+import { test, expect } from "@playwright/test";
+import { open, serve } from "./helpers";
+
+test("browser logs", async ({ page, request }) => {
+  const id = Date.now();
+  await serve(
+    request,
+    "/browser-log/",
+    `<script>
+       setTimeout(() => console.warn("careful ${id}"));
+       setTimeout(() => {
+         throw new Error("boom ${id}");
+       });
+     </script>`,
+  );
+  await open(page, "/browser-log/");
+
+  // Console output and uncaught errors are reported to the server.
+  const logs = async () => {
+    const response = await request.get("/browser-logs");
+    return (await response.text()).split("\n");
+  };
+  await expect
+    .poll(logs)
+    .toContainEqual(expect.stringMatching(`^Console Warn: careful ${id}`));
+  await expect
+    .poll(logs)
+    .toContainEqual(expect.stringMatching(`^Error Error: .*boom ${id}`));
+});
+
+test("browser logs from other origins", async ({ request }) => {
+  const response = await request.post("/_tower-livereload/log", {
+    headers: { origin: "https://example.com" },
+    form: { kind: "error", level: "error", message: "fake" },
+  });
+  expect(response.status()).toBe(403);
+});
@@ -0,0 +1,34 @@
+import { test, expect } from "@playwright/test";
+import { open, send, serve } from "./helpers";
+
+// Failed builds are shown to all pages, so always finish with a success.
+test.afterEach(async ({ request }) => {
+  await send(request, "build succeeded");
+});
+
+test("build overlay", async ({ page, request }) => {
+  await serve(request, "/build/", "<h1>Build</h1>");
+  await open(page, "/build/");
+
+  await send(request, "build started");
+  await expect(page.getByText("Rebuilding…")).toBeVisible();
+
+  await send(request, "build failed expected `;`");
+  await expect(page.getByText("Build failed")).toBeVisible();
+  await expect(page.getByText("expected `;`")).toBeVisible();
+
+  // Successful builds clear the overlay and reload the page.
+  const reload = page.waitForEvent("load");
+  await send(request, "build succeeded");
+  await reload;
+  await expect(page.getByText("Build failed")).toBeHidden();
+});
+
+test("build overlay on connect", async ({ page, request }) => {
+  await serve(request, "/build/late/", "<h1>Build</h1>");
+  await send(request, "build failed expected `;`");
+
+  // Pages opened after a build has failed still show the failure.
+  await open(page, "/build/late/");
+  await expect(page.getByText("Build failed")).toBeVisible();
+});
@@ -0,0 +1,53 @@
+import { test, expect, Page } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+// Pages opened by Playwright are always visible, so hide them by overriding
+// the visibility state instead.
+const hide = (page: Page) =>
+  page.addInitScript(() => {
+    let state = "hidden";
+    Object.defineProperty(document, "visibilityState", {
+      get: () => state,
+      configurable: true,
+    });
+    (window as any).show = () => {
+      state = "visible";
+      document.dispatchEvent(new Event("visibilitychange"));
+    };
+  });
+
+test("deferred reload", async ({ page, request }) => {
+  await serve(request, "/defer/", "<h1>Hidden</h1>");
+  await hide(page);
+  await open(page, "/defer/");
+  await mark(page);
+
+  // Hidden pages do not reload while they are hidden...
+  await send(request, "reload");
+  await page.waitForTimeout(500);
+  expect(await isMarked(page)).toBe(true);
+
+  // ...but reload once they become visible again.
+  const reload = page.waitForEvent("load");
+  await page.evaluate(() => setTimeout(() => (window as any).show()));
+  await reload;
+  expect(await isMarked(page)).toBe(false);
+});
+
+test("deferred stylesheet update", async ({ page, request }) => {
+  await serve(request, "/defer/app.css", "h1 { color: rgb(255, 0, 0); }", "text/css");
+  await serve(
+    request,
+    "/defer/styles/",
+    '<link rel="stylesheet" href="/defer/app.css"><h1>Hidden</h1>',
+  );
+  await hide(page);
+  await open(page, "/defer/styles/");
+  await mark(page);
+
+  // Updates that do not reload the page are applied right away.
+  await serve(request, "/defer/app.css", "h1 { color: rgb(0, 128, 0); }", "text/css");
+  await send(request, "css /defer/app.css");
+  await expect(page.locator("h1")).toHaveCSS("color", "rgb(0, 128, 0)");
+  expect(await isMarked(page)).toBe(true);
+});
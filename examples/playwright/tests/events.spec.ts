@@ -0,0 +1,77 @@
+import { test, expect, Page } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+// Record the details of the given events, and cancel those matching the given
+// condition.
+const listen = (type: string, cancel: string) => `
+  <script>
+    window.received = window.received ?? [];
+    addEventListener("tower-livereload:${type}", (event) => {
+      window.received.push(event.detail);
+      if (${cancel}) {
+        event.preventDefault();
+      }
+    });
+  </script>
+`;
+
+const received = (page: Page) => page.evaluate(() => (window as any).received);
+
+test("cancel reload", async ({ page, request }) => {
+  await serve(request, "/events/cancel/", listen("before-reload", "true"));
+  await open(page, "/events/cancel/");
+  await mark(page);
+
+  await send(request, "reload");
+
+  await expect
+    .poll(() => received(page))
+    .toEqual([{ reason: "reload", strategy: "reload" }]);
+  expect(await isMarked(page)).toBe(true);
+});
+
+test("cancel message", async ({ page, request }) => {
+  await serve(
+    request,
+    "/events/message/",
+    listen("message", `event.detail.type === "reload"`) +
+      listen("before-reload", "false"),
+  );
+  await open(page, "/events/message/");
+  await mark(page);
+
+  await send(request, "reload");
+
+  // Cancelled messages are not handled at all.
+  await expect
+    .poll(() => received(page))
+    .toContainEqual(expect.objectContaining({ type: "reload" }));
+  expect(await received(page)).not.toContainEqual(
+    expect.objectContaining({ reason: "reload" }),
+  );
+  expect(await isMarked(page)).toBe(true);
+});
+
+test("custom event", async ({ page, request }) => {
+  await serve(request, "/events/custom/", listen("custom:greet", "false"));
+  await open(page, "/events/custom/");
+
+  await send(request, "event greet hello world");
+
+  await expect.poll(() => received(page)).toEqual(["hello world"]);
+});
+
+test("custom event named like a built-in event", async ({ page, request }) => {
+  await serve(
+    request,
+    "/events/custom/reserved/",
+    listen("before-reload", "false") +
+      listen("custom:before-reload", "false"),
+  );
+  await open(page, "/events/custom/reserved/");
+
+  await send(request, "event before-reload hello");
+
+  // Only listeners of the custom event receive it.
+  await expect.poll(() => received(page)).toEqual(["hello"]);
+});
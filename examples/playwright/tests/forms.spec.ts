@@ -0,0 +1,37 @@
+import { test, expect } from "@playwright/test";
+import { open, send, serve } from "./helpers";
+
+test("form values", async ({ page, request }) => {
+  await serve(
+    request,
+    "/forms/",
+    `<form>
+       <input name="title">
+       <input name="tag"><input name="tag">
+       <input name="draft" type="checkbox">
+       <select name="kind"><option>post</option><option>page</option></select>
+       <textarea name="body"></textarea>
+       <input name="secret" type="password">
+     </form>`,
+  );
+  await open(page, "/forms/");
+  await page.locator("[name=title]").fill("Hello");
+  await page.locator("[name=tag]").nth(1).fill("second");
+  await page.locator("[name=draft]").check();
+  await page.locator("[name=kind]").selectOption("page");
+  await page.locator("[name=body]").fill("Some text");
+  await page.locator("[name=secret]").fill("hunter2");
+
+  const reload = page.waitForEvent("load");
+  await send(request, "reload");
+  await reload;
+
+  // Values are restored after reloading, except for passwords.
+  await expect(page.locator("[name=title]")).toHaveValue("Hello");
+  await expect(page.locator("[name=tag]").nth(0)).toHaveValue("");
+  await expect(page.locator("[name=tag]").nth(1)).toHaveValue("second");
+  await expect(page.locator("[name=draft]")).toBeChecked();
+  await expect(page.locator("[name=kind]")).toHaveValue("page");
+  await expect(page.locator("[name=body]")).toHaveValue("Some text");
+  await expect(page.locator("[name=secret]")).toHaveValue("");
+});
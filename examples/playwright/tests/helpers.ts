@@ -0,0 +1,61 @@
+import { APIRequestContext, Page } from "@playwright/test";
+
+/**
+ * Serve the given content at the given path of the test server.
+ */
+export const serve = async (
+  request: APIRequestContext,
+  path: string,
+  body: string,
+  contentType = "text/html; charset=utf-8",
+) => {
+  await request.put(`/files${path}`, {
+    data: body,
+    headers: { "content-type": contentType },
+  });
+};
+
+/**
+ * Stop serving the given path, so that requests for it fail.
+ */
+export const unserve = async (request: APIRequestContext, path: string) => {
+  await request.delete(`/files${path}`);
+};
+
+/**
+ * Send a command to connected pages using the trigger route.
+ */
+export const send = async (request: APIRequestContext, command: string) => {
+  const response = await request.post("/_tower-livereload/trigger", {
+    data: command,
+  });
+  return (await response.text()).trim();
+};
+
+/**
+ * Open the given page and wait until it has connected to the event stream.
+ */
+export const open = async (page: Page, path: string) => {
+  const connected = page.waitForEvent("console", {
+    predicate: (message) => message.text() === "connected",
+  });
+  await page.addInitScript(() => {
+    addEventListener("tower-livereload:connected", () => {
+      console.log("connected");
+    });
+  });
+  await page.goto(path);
+  await connected;
+};
+
+/**
+ * Mark the current document, so that reloads can be detected by checking
+ * whether the mark is still present.
+ */
+export const mark = (page: Page) =>
+  page.evaluate(() => {
+    (window as any).marked = true;
+  });
+
+export const isMarked = (page: Page) =>
+  page.evaluate(() => (window as any).marked === true);
@@ -0,0 +1,54 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+// A module that counts how often it has been loaded, keeping the count across
+// replacements.
+const counter = (version: string, accept = true) => `
+  const hot = window.towerLiveReload?.hot(import.meta.url);
+  const count = (hot?.data.count ?? 0) + 1;
+  document.querySelector("h1").textContent = "${version} " + count;
+  hot?.dispose((data) => {
+    data.count = count;
+  });
+  ${accept ? "hot?.accept();" : ""}
+`;
+
+test("module replacement", async ({ page, request }) => {
+  await serve(request, "/modules/counter.js", counter("v1"), "text/javascript");
+  await serve(
+    request,
+    "/modules/",
+    '<h1></h1><script type="module" src="/modules/counter.js"></script>',
+  );
+  await open(page, "/modules/");
+  await expect(page.locator("h1")).toHaveText("v1 1");
+  await mark(page);
+
+  await serve(request, "/modules/counter.js", counter("v2"), "text/javascript");
+  await send(request, "module /modules/counter.js");
+
+  // The new module is loaded with the state of the old one.
+  await expect(page.locator("h1")).toHaveText("v2 2");
+  expect(await isMarked(page)).toBe(true);
+});
+
+test("module replacement fallback", async ({ page, request }) => {
+  await serve(
+    request,
+    "/modules/static.js",
+    counter("v1", false),
+    "text/javascript",
+  );
+  await serve(
+    request,
+    "/modules/static/",
+    '<h1></h1><script type="module" src="/modules/static.js"></script>',
+  );
+  await open(page, "/modules/static/");
+  await expect(page.locator("h1")).toHaveText("v1 1");
+
+  // Modules that do not accept updates cause a full reload.
+  const reload = page.waitForRequest("/modules/static/");
+  await send(request, "module /modules/static.js");
+  await reload;
+});
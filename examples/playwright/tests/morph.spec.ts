@@ -0,0 +1,45 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+const form = `
+  <input id="name">
+  <details><summary>More</summary>Details</details>
+`;
+
+test("morph", async ({ page, request }) => {
+  await serve(request, "/morph/", `<h1>Before</h1>${form}`);
+  await open(page, "/morph/");
+  await page.locator("#name").fill("typed");
+  await page.locator("summary").click();
+  await mark(page);
+
+  await serve(request, "/morph/", `<h1>After</h1>${form}`);
+  const acknowledged = page.waitForRequest(/\/acknowledge\?/);
+  await send(request, "reload");
+  await acknowledged;
+
+  // Changes are merged into the page, while unchanged elements keep their
+  // state.
+  await expect(page.locator("h1")).toHaveText("After");
+  await expect(page.locator("#name")).toHaveValue("typed");
+  await expect(page.locator("details")).toHaveAttribute("open");
+  await expect(page.locator("script[data-event-stream]")).toHaveCount(1);
+  expect(await isMarked(page)).toBe(true);
+});
+
+test("morph fallback", async ({ page, request }) => {
+  const withScript = (src: string) =>
+    `<head><script src="${src}"></script></head><h1>Scripts</h1>`;
+  await serve(request, "/morph/a.js", "", "text/javascript");
+  await serve(request, "/morph/b.js", "", "text/javascript");
+  await serve(request, "/morph/scripts/", withScript("/morph/a.js"));
+  await open(page, "/morph/scripts/");
+  await mark(page);
+
+  // Changed scripts cannot be merged, so the page is reloaded instead.
+  await serve(request, "/morph/scripts/", withScript("/morph/b.js"));
+  const reload = page.waitForEvent("load");
+  await send(request, "reload");
+  await reload;
+  expect(await isMarked(page)).toBe(false);
+});
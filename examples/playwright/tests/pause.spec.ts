@@ -0,0 +1,27 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+// Pausing affects all pages, so never leave the server paused.
+test.afterEach(async ({ request }) => {
+  await send(request, "resume");
+});
+
+test("pause and resume", async ({ page, request }) => {
+  await serve(request, "/pause/", "<h1>Pause</h1>");
+  await open(page, "/pause/");
+  await mark(page);
+
+  expect(await send(request, "pause")).toBe("ok");
+  await expect(page.getByText("Live reload paused")).toBeVisible();
+
+  // Reloads are held back while paused...
+  await send(request, "reload");
+  await page.waitForTimeout(500);
+  expect(await isMarked(page)).toBe(true);
+
+  // ...and sent once resumed.
+  const reload = page.waitForEvent("load");
+  await send(request, "resume");
+  await reload;
+  await expect(page.getByText("Live reload paused")).toBeHidden();
+});
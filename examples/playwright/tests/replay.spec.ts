@@ -0,0 +1,26 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, send, serve } from "./helpers";
+
+test("reload before connecting", async ({ page, request }) => {
+  await serve(request, "/replay/", "<h1>Replay</h1>");
+
+  // Hold back the connection to the event stream until a reload has been sent.
+  let connect = () => {};
+  const connecting = new Promise<void>((resolve) => {
+    connect = resolve;
+  });
+  await page.route("**/event-stream*", async (route) => {
+    await connecting;
+    await route.continue();
+  });
+
+  await page.goto("/replay/");
+  await mark(page);
+  await send(request, "reload");
+
+  // The page still picks up the reload once it connects.
+  const reload = page.waitForEvent("load");
+  connect();
+  await reload;
+  expect(await isMarked(page)).toBe(false);
+});
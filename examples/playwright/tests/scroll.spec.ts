@@ -0,0 +1,30 @@
+import { test, expect } from "@playwright/test";
+import { open, send, serve } from "./helpers";
+
+test("scroll position", async ({ page, request }) => {
+  await serve(
+    request,
+    "/scroll/",
+    `<div id="list" style="height: 100px; overflow: auto">
+       <div style="height: 1000px"></div>
+     </div>
+     <div style="height: 5000px"></div>`,
+  );
+  await open(page, "/scroll/");
+  await page.evaluate(() => {
+    document.getElementById("list")!.scrollTo(0, 300);
+    scrollTo(0, 1200);
+  });
+
+  const reload = page.waitForEvent("load");
+  await send(request, "reload");
+  await reload;
+
+  // Both the window and scrolled elements are restored after reloading.
+  await expect
+    .poll(() => page.evaluate(() => scrollY))
+    .toBe(1200);
+  await expect
+    .poll(() => page.evaluate(() => document.getElementById("list")!.scrollTop))
+    .toBe(300);
+});
@@ -0,0 +1,93 @@
+import { test, expect, BrowserContext, Page } from "@playwright/test";
+import { isMarked, mark, open, send, serve } from "./helpers";
+
+// Collect the URLs of all event streams opened in the given context.
+const streams = (context: BrowserContext) => {
+  const urls: string[] = [];
+  context.on("request", (request) => {
+    if (request.url().includes("/event-stream")) {
+      urls.push(request.url());
+    }
+  });
+  return urls;
+};
+
+test("shared connection", async ({ context, request }) => {
+  await serve(request, "/shared/", "<h1>Shared</h1>");
+  const opened = streams(context);
+  const first = await context.newPage();
+  const second = await context.newPage();
+  await open(first, "/shared/");
+  await open(second, "/shared/");
+
+  // Both pages receive events over a single connection.
+  expect(opened).toHaveLength(1);
+  const reloads = [first, second].map((page) => page.waitForEvent("load"));
+  await send(request, "reload");
+  await Promise.all(reloads);
+});
+
+test("shared connection handover", async ({ context, request }) => {
+  await serve(request, "/shared/handover/", "<h1>Shared</h1>");
+  const opened = streams(context);
+  const first = await context.newPage();
+  const second = await context.newPage();
+  await open(first, "/shared/handover/");
+  await open(second, "/shared/handover/");
+  await mark(second);
+
+  // Once the page holding the connection is closed, another page takes over
+  // and resumes where the previous connection left off.
+  await first.close();
+  await expect.poll(() => opened.length).toBe(2);
+  expect(opened[1]).toContain("last-event-id=");
+
+  const reload = second.waitForEvent("load");
+  await send(request, "reload");
+  await reload;
+  expect(await isMarked(second)).toBe(false);
+});
+
+// Wait until the given page has registered with the connection of another.
+const registered = (page: Page) =>
+  page.waitForResponse((response) =>
+    response.url().includes("/_tower-livereload/share?"),
+  );
+
+test("shared connection targeting", async ({ context, request }) => {
+  await serve(request, "/shared/docs/", "<h1>Docs</h1>");
+  await serve(request, "/shared/blog/", "<h1>Blog</h1>");
+  const opened = streams(context);
+  const docs = await context.newPage();
+  const blog = await context.newPage();
+  await open(docs, "/shared/docs/");
+  const following = registered(blog);
+  await open(blog, "/shared/blog/");
+  await following;
+  await mark(docs);
+
+  // Pages of different URLs share a connection, but targeted reloads still
+  // only reach matching pages.
+  expect(opened).toHaveLength(1);
+  const reload = blog.waitForEvent("load");
+  await send(request, "reload /shared/blog/*");
+  await reload;
+  await docs.waitForTimeout(500);
+  expect(await isMarked(docs)).toBe(true);
+});
+
+test("shared connection acknowledgements", async ({ context, request }) => {
+  await serve(request, "/shared/first/", "<h1>First</h1>");
+  await serve(request, "/shared/second/", "<h1>Second</h1>");
+  const first = await context.newPage();
+  const second = await context.newPage();
+  await open(first, "/shared/first/");
+  const following = registered(second);
+  await open(second, "/shared/second/");
+  await following;
+
+  // The server knows about all pages, not just the one holding the
+  // connection.
+  const response = await request.post("/reload-and-wait");
+  expect(await response.text()).toMatch(/^acknowledged=2 /);
+});
@@ -0,0 +1,70 @@
+import { test, expect } from "@playwright/test";
+import { isMarked, mark, open, send, serve, unserve } from "./helpers";
+
+const page = (path: string) =>
+  `<link rel="stylesheet" href="${path}"><h1>Styled</h1>`;
+const red = "h1 { color: rgb(255, 0, 0); }";
+const green = "h1 { color: rgb(0, 128, 0); }";
+
+test("stylesheet update", async ({ page: tab, request }) => {
+  await serve(request, "/styles/app.css", red, "text/css");
+  await serve(request, "/styles/", page("/styles/app.css"));
+  await open(tab, "/styles/");
+  await expect(tab.locator("h1")).toHaveCSS("color", "rgb(255, 0, 0)");
+  await mark(tab);
+
+  await serve(request, "/styles/app.css", green, "text/css");
+  await send(request, "css /styles/app.css");
+
+  // The stylesheet is replaced without reloading the page.
+  await expect(tab.locator("h1")).toHaveCSS("color", "rgb(0, 128, 0)");
+  await expect(tab.locator("link[rel=stylesheet]")).toHaveCount(1);
+  expect(await isMarked(tab)).toBe(true);
+});
+
+test("stylesheet update failure", async ({ page: tab, request }) => {
+  await serve(request, "/styles/broken.css", red, "text/css");
+  await serve(request, "/styles/broken/", page("/styles/broken.css"));
+  await open(tab, "/styles/broken/");
+
+  await unserve(request, "/styles/broken.css");
+  const failed = tab.waitForResponse(
+    (response) =>
+      response.url().includes("/styles/broken.css?livereload=") &&
+      response.status() === 404,
+  );
+  await send(request, "css /styles/broken.css");
+  await failed;
+
+  // The previous version is kept, and the failed copy is removed.
+  await expect(tab.locator("link[rel=stylesheet]")).toHaveCount(1);
+  await expect(tab.locator("link[rel=stylesheet]")).not.toHaveAttribute(
+    "href",
+    /livereload=/,
+  );
+  await expect(tab.locator("h1")).toHaveCSS("color", "rgb(255, 0, 0)");
+});
+
+test("stylesheet update survives morph", async ({ page: tab, request }) => {
+  await serve(request, "/morph/styles.css", red, "text/css");
+  await serve(request, "/morph/styles/", page("/morph/styles.css"));
+  await open(tab, "/morph/styles/");
+
+  await serve(request, "/morph/styles.css", green, "text/css");
+  await send(request, "css /morph/styles.css");
+  await expect(tab.locator("link[rel=stylesheet]")).toHaveCount(1);
+  await expect(tab.locator("link[rel=stylesheet]")).toHaveAttribute(
+    "href",
+    /livereload=/,
+  );
+
+  // Morphing the page must not switch back to the stale stylesheet.
+  const acknowledged = tab.waitForRequest(/\/acknowledge\?/);
+  await send(request, "reload");
+  await acknowledged;
+  await expect(tab.locator("link[rel=stylesheet]")).toHaveAttribute(
+    "href",
+    /livereload=/,
+  );
+  await expect(tab.locator("h1")).toHaveCSS("color", "rgb(0, 128, 0)");
+});
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+
+/// Collects acknowledgements from clients that have reloaded their page.
+#[derive(Debug, Default)]
+pub struct Acknowledgements {
+    waiters: Mutex<Vec<Arc<Waiter>>>,
+    notify: Notify,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    after: u64,
+    /// Tokens of the clients that have not acknowledged the reload yet.
+    pending: Mutex<HashSet<String>>,
+}
+
+impl Acknowledgements {
+    /// Record that the client with the given token has loaded its page after
+    /// the reload with the given generation.
+    pub fn acknowledge(&self, id: u64, token: &str) {
+        for waiter in self.waiters.lock().unwrap().iter() {
+            if id > waiter.after {
+                waiter.pending.lock().unwrap().remove(token);
+            }
+        }
+
+        self.notify.notify_waiters();
+    }
+
+    /// Run the given function, then wait until all expected clients have
+    /// acknowledged a reload newer than the given generation.
+    ///
+    /// Returns the number of distinct clients that have acknowledged the
+    /// reload, which is lower than expected if the timeout has elapsed.
+    pub async fn wait<F: FnOnce()>(
+        &self,
+        after: u64,
+        expected: HashSet<String>,
+        timeout: Duration,
+        send: F,
+    ) -> usize {
+        let total = expected.len();
+        let waiter = Arc::new(Waiter {
+            after,
+            pending: Mutex::new(expected),
+        });
+        self.waiters.lock().unwrap().push(waiter.clone());
+        send();
+
+        let _ = tokio::time::timeout(timeout, async {
+            loop {
+                let notified = self.notify.notified();
+                if waiter.pending.lock().unwrap().is_empty() {
+                    break;
+                }
+                notified.await;
+            }
+        })
+        .await;
+
+        self.waiters
+            .lock()
+            .unwrap()
+            .retain(|other| !Arc::ptr_eq(other, &waiter));
+
+        let pending = waiter.pending.lock().unwrap().len();
+        total - pending
+    }
+}
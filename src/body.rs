@@ -0,0 +1,46 @@
+use std::{convert::Infallible, pin::Pin, task::Poll};
+
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+use crate::sse::ReloadEventsBody;
+
+/// Body of responses to internal routes.
+pub enum InternalBody {
+    Events(Box<ReloadEventsBody>),
+    Full(Option<Bytes>),
+}
+
+impl InternalBody {
+    pub fn empty() -> Self {
+        InternalBody::Full(None)
+    }
+}
+
+impl Body for InternalBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.get_mut() {
+            InternalBody::Events(events) => Pin::new(events.as_mut()).poll_frame(cx),
+            InternalBody::Full(data) => Poll::Ready(data.take().map(|data| Ok(Frame::data(data)))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        matches!(self, InternalBody::Full(None))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self {
+            InternalBody::Events(_) => SizeHint::default(),
+            InternalBody::Full(data) => {
+                SizeHint::with_exact(data.as_ref().map_or(0, |data| data.len() as u64))
+            }
+        }
+    }
+}
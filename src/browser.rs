@@ -0,0 +1,192 @@
+//! Errors and console output reported by the browser.
+//!
+//! Once a handler has been registered using
+//! [`LiveReloadLayer::on_browser_log`](crate::LiveReloadLayer::on_browser_log),
+//! injected pages report uncaught errors, unhandled promise rejections and
+//! resources that failed to load back to the server. Optionally, calls to
+//! `console.*` are reported as well, see
+//! [`LiveReloadLayer::capture_console`](crate::LiveReloadLayer::capture_console).
+
+use std::{net::SocketAddr, sync::Arc};
+
+use crate::{event::LogLevel, query};
+
+/// Kind of a [`BrowserLog`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BrowserLogKind {
+    /// An uncaught JavaScript error.
+    Error,
+    /// A promise rejection that was not handled.
+    UnhandledRejection,
+    /// A resource, such as an image or script, that failed to load.
+    Resource,
+    /// A call to one of the `console.*` methods.
+    Console,
+}
+
+/// A message reported by a page injected with live-reload logic.
+#[derive(Clone, Debug)]
+pub struct BrowserLog {
+    kind: BrowserLogKind,
+    level: LogLevel,
+    message: String,
+    source: Option<String>,
+    stack: Option<String>,
+    url: Option<String>,
+    user_agent: Option<String>,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl BrowserLog {
+    /// Parse a message from the form-encoded body sent by the client.
+    pub(crate) fn parse(
+        form: &str,
+        user_agent: Option<String>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Option<Self> {
+        let kind = match query::get_form(form, "kind")?.as_str() {
+            "error" => BrowserLogKind::Error,
+            "rejection" => BrowserLogKind::UnhandledRejection,
+            "resource" => BrowserLogKind::Resource,
+            "console" => BrowserLogKind::Console,
+            _ => return None,
+        };
+        let level = match query::get_form(form, "level")?.as_str() {
+            "trace" => LogLevel::Trace,
+            "debug" => LogLevel::Debug,
+            "log" | "info" => LogLevel::Info,
+            "warn" => LogLevel::Warn,
+            "error" => LogLevel::Error,
+            _ => return None,
+        };
+
+        Some(BrowserLog {
+            kind,
+            level,
+            message: query::get_form(form, "message").unwrap_or_default(),
+            source: query::get_form(form, "source").filter(|source| !source.is_empty()),
+            stack: query::get_form(form, "stack").filter(|stack| !stack.is_empty()),
+            url: query::get_form(form, "url"),
+            user_agent,
+            remote_addr,
+        })
+    }
+
+    /// Return the kind of the message.
+    pub fn kind(&self) -> BrowserLogKind {
+        self.kind
+    }
+
+    /// Return the severity of the message.
+    ///
+    /// Errors, rejections and failed resources are always reported as
+    /// [`LogLevel::Error`], while console output uses the level of the called
+    /// method, e.g. [`LogLevel::Warn`] for `console.warn`.
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// Return the text of the message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return where the message originated, if known.
+    ///
+    /// For errors, this is the location in the script that threw the error,
+    /// e.g. `http://localhost:3000/app.js:12:5`. For failed resources, this is
+    /// the URL of the resource.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Return the stack trace of the error, if available.
+    pub fn stack(&self) -> Option<&str> {
+        self.stack.as_deref()
+    }
+
+    /// Return the URL of the page that reported the message.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Return the [`User-Agent`] of the browser that reported the message, if
+    /// any.
+    ///
+    /// [`User-Agent`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/User-Agent
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Return the remote address of the browser that reported the message, if
+    /// known.
+    ///
+    /// By default, the remote address is only known if the server inserts it
+    /// into the request extensions as a [`SocketAddr`]. See
+    /// [`LiveReloadLayer::remote_addr`](crate::LiveReloadLayer::remote_addr)
+    /// for other servers.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+}
+
+/// Handler registered using
+/// [`LiveReloadLayer::on_browser_log`](crate::LiveReloadLayer::on_browser_log).
+#[derive(Clone)]
+pub(crate) struct Handler(pub Arc<dyn Fn(BrowserLog) + Send + Sync>);
+
+impl std::fmt::Debug for Handler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handler").field(&"...").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error() {
+        let form = "kind=error&level=error&message=Uncaught+TypeError&source=http%3A%2F%2Flocalhost%2Fapp.js%3A12%3A5&stack=&url=http%3A%2F%2Flocalhost%2F";
+        let addr = "127.0.0.1:5000".parse().unwrap();
+        let log = BrowserLog::parse(form, Some("Firefox".to_owned()), Some(addr)).unwrap();
+
+        assert_eq!(log.kind(), BrowserLogKind::Error);
+        assert_eq!(log.level(), LogLevel::Error);
+        assert_eq!(log.message(), "Uncaught TypeError");
+        assert_eq!(log.source(), Some("http://localhost/app.js:12:5"));
+        assert_eq!(log.stack(), None);
+        assert_eq!(log.url(), Some("http://localhost/"));
+        assert_eq!(log.user_agent(), Some("Firefox"));
+        assert_eq!(log.remote_addr(), Some(addr));
+    }
+
+    #[test]
+    fn parse_console_levels() {
+        let level = |level: &str| {
+            BrowserLog::parse(&format!("kind=console&level={}", level), None, None)
+                .map(|log| log.level())
+        };
+
+        assert_eq!(level("trace"), Some(LogLevel::Trace));
+        assert_eq!(level("debug"), Some(LogLevel::Debug));
+        assert_eq!(level("log"), Some(LogLevel::Info));
+        assert_eq!(level("info"), Some(LogLevel::Info));
+        assert_eq!(level("warn"), Some(LogLevel::Warn));
+        assert_eq!(level("error"), Some(LogLevel::Error));
+        assert_eq!(level("fatal"), None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(BrowserLog::parse("", None, None).is_none());
+        assert!(BrowserLog::parse("kind=error", None, None).is_none());
+        assert!(BrowserLog::parse("kind=crash&level=error", None, None).is_none());
+
+        let log = BrowserLog::parse("kind=rejection&level=error", None, None).unwrap();
+        assert_eq!(log.kind(), BrowserLogKind::UnhandledRejection);
+        assert_eq!(log.message(), "");
+        assert_eq!(log.url(), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Forward Cargo build results to connected clients.
+//!
+//! This module reads the JSON messages emitted by Cargo when it is run using
+//! `--message-format=json`, and reports the status of each build using
+//! [`ReloadEvent::Build`]. Compiler errors are displayed in an overlay on the
+//! page, and successful builds clear the overlay and reload the page.
+//!
+//! # Example
+//!
+//! ```
+//! use std::process::Command;
+//!
+//! use tower_livereload::LiveReloadLayer;
+//!
+//! # fn example() -> std::io::Result<()> {
+//! let livereload = LiveReloadLayer::new();
+//! let reloader = livereload.reloader();
+//! std::thread::spawn(move || {
+//!     let mut command = Command::new("cargo");
+//!     command.args(["build", "--message-format=json"]);
+//!     tower_livereload::cargo::run(&mut command, &reloader)
+//! });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Note that all functions in this module are blocking, and as such should not
+//! be called from within an asynchronous context.
+
+use std::{
+    io::{self, BufRead, BufReader},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use serde_json::Value;
+
+use crate::{
+    event::{BuildStatus, Diagnostic, ReloadEvent, Span},
+    Reloader,
+};
+
+/// Read Cargo JSON messages from the given reader and forward the results of
+/// each build to clients.
+///
+/// A build is reported as started once its first message has been read, and
+/// as succeeded or failed once Cargo reports that it has finished. Lines that
+/// are not Cargo messages, such as output of programs run by `cargo run`, are
+/// ignored.
+///
+/// This function returns once the reader has been exhausted.
+pub fn forward<R: BufRead>(mut reader: R, reloader: &Reloader) -> io::Result<()> {
+    let mut building = false;
+    let mut diagnostics = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        // Programs run by `cargo run` may print anything, including invalid
+        // UTF-8, which must not stop forwarding.
+        let Ok(message) = serde_json::from_slice::<Value>(&line) else {
+            continue;
+        };
+        if !matches!(message["reason"].as_str(), Some(reason) if REASONS.contains(&reason)) {
+            continue;
+        }
+
+        if !building {
+            building = true;
+            reloader.build_started();
+        }
+
+        match message["reason"].as_str() {
+            Some("compiler-message") => diagnostics.extend(diagnostic(&message["message"])),
+            Some("build-finished") => {
+                building = false;
+                let diagnostics = std::mem::take(&mut diagnostics);
+                if message["success"].as_bool() == Some(true) {
+                    reloader.build_succeeded();
+                } else if diagnostics.is_empty() {
+                    reloader.build_failed("Cargo did not report any errors.");
+                } else {
+                    reloader.send(ReloadEvent::Build(BuildStatus::Diagnostics(diagnostics)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawn the given Cargo command and forward the results of its builds to
+/// clients.
+///
+/// The command must be configured to emit JSON messages, e.g. by passing
+/// `--message-format=json`. Its standard output is captured, while standard
+/// error is left untouched.
+///
+/// This function returns once the command has exited.
+pub fn run(command: &mut Command, reloader: &Reloader) -> io::Result<ExitStatus> {
+    let mut child = command.stdout(Stdio::piped()).spawn()?;
+    let forwarded = match child.stdout.take() {
+        Some(stdout) => forward(BufReader::new(stdout), reloader),
+        None => Ok(()),
+    };
+
+    // Always reap the child, even if reading its output has failed.
+    let status = child.wait();
+    forwarded?;
+    status
+}
+
+/// Values of the `reason` field of messages emitted by Cargo itself.
+const REASONS: &[&str] = &[
+    "compiler-message",
+    "compiler-artifact",
+    "build-script-executed",
+    "build-finished",
+];
+
+/// Convert a compiler diagnostic to a [`Diagnostic`], unless it is less severe
+/// than an error.
+fn diagnostic(message: &Value) -> Option<Diagnostic> {
+    let level = message["level"].as_str()?;
+    if !level.starts_with("error") {
+        return None;
+    }
+
+    let spans = message["spans"].as_array();
+    let span = spans
+        .and_then(|spans| {
+            spans
+                .iter()
+                .find(|span| span["is_primary"].as_bool() == Some(true))
+                .or_else(|| spans.first())
+        })
+        .and_then(|span| {
+            let number = |key: &str| span[key].as_u64().map(|n| n as usize);
+            Some(Span {
+                file: span["file_name"].as_str()?.to_owned(),
+                line_start: number("line_start")?,
+                line_end: number("line_end")?,
+                column_start: number("column_start")?,
+                column_end: number("column_end")?,
+            })
+        });
+
+    Some(Diagnostic {
+        level: level.to_owned(),
+        message: message["message"].as_str().unwrap_or_default().to_owned(),
+        span,
+        rendered: message["rendered"].as_str().map(str::to_owned),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn forward_ignores_program_output() {
+        let reloader = Reloader::new();
+        let output = concat!(
+            "{\"level\":\"info\",\"message\":\"listening\"}\n",
+            "{\"reason\":\"ready\"}\n",
+            "not json\n",
+        );
+        forward(output.as_bytes(), &reloader).unwrap();
+        assert_eq!(reloader.history.latest(), 0);
+
+        let output = concat!(
+            "{\"reason\":\"compiler-artifact\"}\n",
+            "{\"reason\":\"build-finished\",\"success\":true}\n",
+        );
+        forward(output.as_bytes(), &reloader).unwrap();
+        assert_eq!(reloader.history.latest(), 2);
+        assert_eq!(reloader.history.build(), None);
+    }
+
+    #[test]
+    fn forward_skips_invalid_utf8() {
+        let reloader = Reloader::new();
+        let output = b"caf\xe9\n{\"reason\":\"build-finished\",\"success\":true}\n";
+        forward(&output[..], &reloader).unwrap();
+        assert_eq!(reloader.history.latest(), 2);
+        assert_eq!(reloader.history.build(), None);
+    }
+
+    #[test]
+    fn diagnostic_primary_span() {
+        let message = json!({
+            "level": "error",
+            "message": "mismatched types",
+            "rendered": "error[E0308]: mismatched types",
+            "spans": [
+                {
+                    "file_name": "src/lib.rs",
+                    "is_primary": false,
+                    "line_start": 1,
+                    "line_end": 1,
+                    "column_start": 1,
+                    "column_end": 2,
+                },
+                {
+                    "file_name": "src/main.rs",
+                    "is_primary": true,
+                    "line_start": 3,
+                    "line_end": 4,
+                    "column_start": 5,
+                    "column_end": 6,
+                },
+            ],
+        });
+
+        assert_eq!(
+            diagnostic(&message),
+            Some(Diagnostic {
+                level: "error".to_owned(),
+                message: "mismatched types".to_owned(),
+                span: Some(Span {
+                    file: "src/main.rs".to_owned(),
+                    line_start: 3,
+                    line_end: 4,
+                    column_start: 5,
+                    column_end: 6,
+                }),
+                rendered: Some("error[E0308]: mismatched types".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn diagnostic_without_spans() {
+        let message = json!({
+            "level": "error: internal compiler error",
+            "message": "unexpected panic",
+            "spans": [],
+        });
+
+        let diagnostic = diagnostic(&message).unwrap();
+        assert_eq!(diagnostic.level, "error: internal compiler error");
+        assert_eq!(diagnostic.span, None);
+        assert_eq!(diagnostic.rendered, None);
+    }
+
+    #[test]
+    fn diagnostic_skips_warnings() {
+        let message = json!({ "level": "warning", "message": "unused variable", "spans": [] });
+        assert_eq!(diagnostic(&message), None);
+        assert_eq!(diagnostic(&json!({})), None);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    event::{BuildStatus, Payload, ReloadEvent},
+    Reloader,
+};
+
+/// Command to control a [`Reloader`] from outside of the server process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Reload(Option<String>),
+    Send(ReloadEvent),
+    Pause,
+    Resume,
+    Status,
+}
+
+impl Command {
+    /// Parse a single command, such as `reload` or `css /static/app.css`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (input, None),
+        };
+
+        match (name, argument) {
+            ("reload", pattern) => Ok(Command::Reload(pattern.map(ToOwned::to_owned))),
+            ("css" | "stylesheet", Some(path)) => {
+                Ok(Command::Send(ReloadEvent::Stylesheet(path.to_owned())))
+            }
+            ("asset", Some(path)) => Ok(Command::Send(ReloadEvent::Asset(path.to_owned()))),
+            ("module", Some(path)) => Ok(Command::Send(ReloadEvent::Module(path.to_owned()))),
+            ("event", Some(argument)) => {
+                let (name, text) = argument
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((argument, ""));
+                Ok(Command::Send(ReloadEvent::custom(
+                    name,
+                    Payload::text(text.trim_start()),
+                )))
+            }
+            ("build", Some(argument)) => {
+                let (status, message) = argument
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((argument, ""));
+                let status = match (status, message.trim_start()) {
+                    ("started", "") => BuildStatus::Started,
+                    ("failed", message) => BuildStatus::Failed(message.to_owned()),
+                    ("succeeded", "") => BuildStatus::Succeeded,
+                    _ => return Err(format!("invalid build status: {}", argument)),
+                };
+                Ok(Command::Send(ReloadEvent::Build(status)))
+            }
+            ("pause", None) => Ok(Command::Pause),
+            ("resume", None) => Ok(Command::Resume),
+            ("status", None) => Ok(Command::Status),
+            ("css" | "stylesheet" | "asset" | "module" | "event" | "build", None) => {
+                Err(format!("missing argument for command: {}", name))
+            }
+            ("pause" | "resume" | "status", Some(_)) => {
+                Err(format!("unexpected argument for command: {}", name))
+            }
+            (name, _) => Err(format!("unknown command: {}", name)),
+        }
+    }
+
+    /// Run the command using the given [`Reloader`] and return a single-line
+    /// response.
+    pub fn execute(self, reloader: &Reloader) -> String {
+        match self {
+            Command::Reload(None) => reloader.reload(),
+            Command::Reload(Some(pattern)) => reloader.reload_matching(pattern),
+            Command::Send(event) => reloader.send(event),
+            Command::Pause => reloader.pause(),
+            Command::Resume => reloader.resume(),
+            Command::Status => {
+                return format!(
+                    "ok clients={} paused={}",
+                    reloader.client_count(),
+                    reloader.is_paused()
+                )
+            }
+        }
+
+        "ok".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reload() {
+        assert_eq!(Command::parse("reload"), Ok(Command::Reload(None)));
+        assert_eq!(
+            Command::parse("  reload   /docs/*  "),
+            Ok(Command::Reload(Some("/docs/*".to_owned())))
+        );
+    }
+
+    #[test]
+    fn parse_updates() {
+        let stylesheet = Ok(Command::Send(ReloadEvent::Stylesheet(
+            "/static/app.css".to_owned(),
+        )));
+        assert_eq!(Command::parse("css /static/app.css"), stylesheet);
+        assert_eq!(Command::parse("stylesheet /static/app.css"), stylesheet);
+        assert_eq!(
+            Command::parse("asset /logo.png"),
+            Ok(Command::Send(ReloadEvent::Asset("/logo.png".to_owned())))
+        );
+        assert_eq!(
+            Command::parse("module /app.js"),
+            Ok(Command::Send(ReloadEvent::Module("/app.js".to_owned())))
+        );
+    }
+
+    #[test]
+    fn parse_event() {
+        assert_eq!(
+            Command::parse("event rebuilt  wasm ready"),
+            Ok(Command::Send(ReloadEvent::custom(
+                "rebuilt",
+                Payload::text("wasm ready")
+            )))
+        );
+        assert_eq!(
+            Command::parse("event rebuilt"),
+            Ok(Command::Send(ReloadEvent::custom(
+                "rebuilt",
+                Payload::text("")
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_build() {
+        assert_eq!(
+            Command::parse("build started"),
+            Ok(Command::Send(ReloadEvent::Build(BuildStatus::Started)))
+        );
+        assert_eq!(
+            Command::parse("build failed  expected `;`"),
+            Ok(Command::Send(ReloadEvent::Build(BuildStatus::Failed(
+                "expected `;`".to_owned()
+            ))))
+        );
+        assert_eq!(
+            Command::parse("build succeeded"),
+            Ok(Command::Send(ReloadEvent::Build(BuildStatus::Succeeded)))
+        );
+        assert!(Command::parse("build started now").is_err());
+        assert!(Command::parse("build finished").is_err());
+    }
+
+    #[test]
+    fn parse_state() {
+        assert_eq!(Command::parse("pause"), Ok(Command::Pause));
+        assert_eq!(Command::parse("resume"), Ok(Command::Resume));
+        assert_eq!(Command::parse("status"), Ok(Command::Status));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Command::parse("css"),
+            Err("missing argument for command: css".to_owned())
+        );
+        assert_eq!(
+            Command::parse("pause now"),
+            Err("unexpected argument for command: pause".to_owned())
+        );
+        assert_eq!(
+            Command::parse("restart"),
+            Err("unknown command: restart".to_owned())
+        );
+    }
+}
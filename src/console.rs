@@ -0,0 +1,165 @@
+//! Forward server logs to the browser console.
+//!
+//! This module provides [`ConsoleLayer`], a [`tracing_subscriber`] layer that
+//! sends [`tracing`] events to connected clients, where they are printed to
+//! the browser console.
+//!
+//! In the other direction, [`trace_browser_log`] can be passed to
+//! [`LiveReloadLayer::on_browser_log`](crate::LiveReloadLayer::on_browser_log)
+//! to emit errors reported by the browser as [`tracing`] events.
+//!
+//! # Example
+//!
+//! ```
+//! use tower_livereload::{console::ConsoleLayer, LiveReloadLayer};
+//! use tracing::Level;
+//! use tracing_subscriber::prelude::*;
+//!
+//! let livereload = LiveReloadLayer::new();
+//! tracing_subscriber::registry()
+//!     .with(tracing_subscriber::fmt::layer())
+//!     .with(ConsoleLayer::new(livereload.reloader()).level(Level::WARN))
+//!     .init();
+//! ```
+//!
+//! [`tracing`]: https://docs.rs/tracing
+//! [`tracing_subscriber`]: https://docs.rs/tracing-subscriber
+
+use std::fmt::{Debug, Write};
+
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::{
+    browser::BrowserLog,
+    event::{LogLevel, LogRecord, ReloadEvent},
+    Reloader,
+};
+
+/// A [`tracing_subscriber`] layer that forwards events to the browser console
+/// of all connected clients.
+///
+/// Note that this layer does not filter events for other layers. Events less
+/// severe than the configured level are only ignored by this layer.
+///
+/// Events emitted by [`trace_browser_log`] are never forwarded, as they
+/// originate from the browser in the first place.
+///
+/// [`tracing_subscriber`]: https://docs.rs/tracing-subscriber
+#[derive(Clone, Debug)]
+pub struct ConsoleLayer {
+    reloader: Reloader,
+    level: Level,
+}
+
+impl ConsoleLayer {
+    /// Create a new [`ConsoleLayer`] that sends events using the given
+    /// [`Reloader`].
+    ///
+    /// By default, events of level [`Level::INFO`] and above are forwarded.
+    pub fn new(reloader: Reloader) -> Self {
+        Self {
+            reloader,
+            level: Level::INFO,
+        }
+    }
+
+    /// Only forward events of the given level and above.
+    pub fn level(self, level: Level) -> Self {
+        Self { level, ..self }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.level || metadata.target() == BROWSER_TARGET {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.reloader.send(ReloadEvent::Log(LogRecord {
+            level: log_level(*metadata.level()),
+            target: metadata.target().to_owned(),
+            message: (visitor.message + &visitor.fields).trim_start().to_owned(),
+        }));
+    }
+}
+
+/// Emit the given message reported by the browser as a [`tracing`] event with
+/// the target `browser`.
+///
+/// Messages reported by pages are printed to the browser console, which is
+/// then forwarded to clients by [`ConsoleLayer`]. As this would otherwise
+/// create a loop, events emitted by this function are never forwarded.
+///
+/// [`tracing`]: https://docs.rs/tracing
+pub fn trace_browser_log(log: BrowserLog) {
+    let url = log.url().unwrap_or_default();
+    let source = log.source().unwrap_or_default();
+    let stack = log.stack().unwrap_or_default();
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(
+                target: BROWSER_TARGET,
+                $level,
+                kind = ?log.kind(),
+                url,
+                source,
+                stack,
+                "{}",
+                log.message()
+            )
+        };
+    }
+
+    match log.level() {
+        LogLevel::Trace => emit!(Level::TRACE),
+        LogLevel::Debug => emit!(Level::DEBUG),
+        LogLevel::Info => emit!(Level::INFO),
+        LogLevel::Warn => emit!(Level::WARN),
+        LogLevel::Error => emit!(Level::ERROR),
+    }
+}
+
+const BROWSER_TARGET: &str = "browser";
+
+fn log_level(level: Level) -> LogLevel {
+    match level {
+        Level::TRACE => LogLevel::Trace,
+        Level::DEBUG => LogLevel::Debug,
+        Level::INFO => LogLevel::Info,
+        Level::WARN => LogLevel::Warn,
+        _ => LogLevel::Error,
+    }
+}
+
+/// Format the message of an event, followed by its other fields as
+/// `key=value` pairs.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Local control socket to trigger reloads from other processes.
+//!
+//! This allows any build tool or script to send events to connected clients,
+//! without having to link against Rust code. Commands are sent as lines of
+//! text, each of which is answered with a single line that either starts with
+//! `ok` or `error`.
+//!
+//! The following commands are supported:
+//!
+//! - `reload`: Reload all pages.
+//! - `reload <pattern>`: Reload pages whose path matches the given pattern,
+//!   see [`Reloader::reload_matching`].
+//! - `css <path>`: Update the stylesheet loaded from the given path.
+//! - `asset <path>`: Update the asset loaded from the given path.
+//! - `module <path>`: Replace the JavaScript module loaded from the given path,
+//!   see [`Reloader::reload_module`].
+//! - `event <name> [text]`: Send a custom event with an optional text payload.
+//! - `build started`, `build failed <message>` and `build succeeded`: Report
+//!   the status of a build, see [`BuildStatus`](crate::event::BuildStatus).
+//! - `pause` and `resume`: Pause or resume reloading.
+//! - `status`: Report the number of connected clients and whether reloading is
+//!   paused, e.g. `ok clients=2 paused=false`.
+//!
+//! # Example
+//!
+//! ```
+//! use tower_livereload::LiveReloadLayer;
+//!
+//! # async fn example() {
+//! let livereload = LiveReloadLayer::new();
+//! tokio::spawn(tower_livereload::control::listen_tcp(
+//!     livereload.reloader(),
+//!     "127.0.0.1:35729",
+//! ));
+//! # }
+//! ```
+//!
+//! Browsers can then be reloaded from a shell, e.g. using `echo reload | nc
+//! 127.0.0.1 35729`.
+//!
+//! Note that the control socket is not authenticated in any way. As such, TCP
+//! sockets should only ever be bound to a loopback address.
+
+use std::io;
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, ToSocketAddrs},
+};
+
+use crate::{command::Command, Reloader};
+
+/// Listen for control commands on the given TCP address.
+///
+/// This function only returns when accepting new connections fails.
+pub async fn listen_tcp<A: ToSocketAddrs>(reloader: Reloader, addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle(reloader.clone(), stream));
+    }
+}
+
+/// Listen for control commands on a Unix domain socket at the given path.
+///
+/// Note that binding fails if a file already exists at the given path, such as
+/// a socket left over from a previous run.
+///
+/// This function only returns when accepting new connections fails.
+#[cfg(unix)]
+pub async fn listen_unix<P: AsRef<std::path::Path>>(reloader: Reloader, path: P) -> io::Result<()> {
+    let listener = tokio::net::UnixListener::bind(path)?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle(reloader.clone(), stream));
+    }
+}
+
+async fn handle<S: AsyncRead + AsyncWrite + Unpin>(
+    reloader: Reloader,
+    stream: S,
+) -> io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match Command::parse(&line) {
+            Ok(command) => command.execute(&reloader),
+            Err(error) => format!("error: {}", error),
+        };
+        writer.write_all(response.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
+    }
+
+    Ok(())
+}
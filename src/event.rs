@@ -0,0 +1,187 @@
+//! Events that can be sent to connected clients.
+//!
+//! Events are sent using [`Reloader::send`](crate::Reloader::send) and are
+//! delivered to every page that has been injected with live-reload logic.
+
+/// An event that is sent to connected clients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReloadEvent {
+    /// Reload the entire page.
+    Reload,
+    /// Update stylesheets that were loaded from the given path, e.g.
+    /// `/static/app.css`.
+    ///
+    /// See [`Reloader::reload_stylesheet`](crate::Reloader::reload_stylesheet)
+    /// for details.
+    Stylesheet(String),
+    /// Update static assets, such as images, that were loaded from the given
+    /// path, e.g. `/static/logo.svg`.
+    ///
+    /// See [`Reloader::reload_asset`](crate::Reloader::reload_asset) for
+    /// details.
+    Asset(String),
+    /// Replace the JavaScript module that was loaded from the given path, e.g.
+    /// `/js/widget.js`.
+    ///
+    /// See [`Reloader::reload_module`](crate::Reloader::reload_module) for
+    /// details.
+    Module(String),
+    /// Report the status of a build, which is displayed in an overlay on the
+    /// page.
+    ///
+    /// See [`BuildStatus`] for how each status is displayed.
+    Build(BuildStatus),
+    /// Print a log message to the browser console of the page.
+    ///
+    /// Unlike other events, log messages are only delivered to clients that
+    /// are connected at the time, and are not held back by
+    /// [`LiveReloadLayer::debounce`](crate::LiveReloadLayer::debounce).
+    /// Clients that fall behind may miss some of them.
+    Log(LogRecord),
+    /// Dispatch a custom event with the given name and payload on the page.
+    ///
+    /// In the browser, custom events are dispatched as a DOM [`CustomEvent`]
+    /// named `tower-livereload:custom:<name>` on `window`, with the payload
+    /// available as its `detail` property.
+    ///
+    /// [`CustomEvent`]: https://developer.mozilla.org/en-US/docs/Web/API/CustomEvent
+    Custom {
+        /// Name of the custom event.
+        name: String,
+        /// Payload of the custom event.
+        payload: Payload,
+    },
+}
+
+impl ReloadEvent {
+    /// Create a new [`ReloadEvent::Custom`] event.
+    pub fn custom<N: Into<String>>(name: N, payload: Payload) -> Self {
+        ReloadEvent::Custom {
+            name: name.into(),
+            payload,
+        }
+    }
+}
+
+/// Status of a build, sent using [`ReloadEvent::Build`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildStatus {
+    /// A build has started, which is displayed as a small spinner.
+    Started,
+    /// A build has failed with the given error message, which is displayed in
+    /// a dismissable overlay covering the page.
+    Failed(String),
+    /// A build has failed with the given diagnostics, which are displayed in
+    /// a dismissable overlay covering the page.
+    Diagnostics(Vec<Diagnostic>),
+    /// A build has succeeded, which reloads the page.
+    Succeeded,
+}
+
+/// A single diagnostic reported by a failed build, such as a compiler error.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Severity of the diagnostic, e.g. `error` or `warning`.
+    pub level: String,
+    /// Short message describing the diagnostic.
+    pub message: String,
+    /// Location of the diagnostic in the source code, if known.
+    pub span: Option<Span>,
+    /// Full human-readable rendering of the diagnostic, if available.
+    pub rendered: Option<String>,
+}
+
+/// Location of a [`Diagnostic`] in the source code.
+///
+/// Line and column numbers start at one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    /// Path of the file containing the span.
+    pub file: String,
+    /// Line on which the span starts.
+    pub line_start: usize,
+    /// Line on which the span ends.
+    pub line_end: usize,
+    /// Column at which the span starts.
+    pub column_start: usize,
+    /// Column at which the span ends.
+    pub column_end: usize,
+}
+
+/// Log message sent using [`ReloadEvent::Log`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord {
+    /// Severity of the message, which determines the console method used to
+    /// print it.
+    pub level: LogLevel,
+    /// Name of the module or component that emitted the message.
+    pub target: String,
+    /// Text of the message.
+    pub message: String,
+}
+
+/// Severity of a [`LogRecord`].
+///
+/// Errors are printed using `console.error`, warnings using `console.warn`
+/// and all other messages using `console.log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Very verbose information.
+    Trace,
+    /// Information useful for debugging.
+    Debug,
+    /// General information.
+    Info,
+    /// Potential problems.
+    Warn,
+    /// Errors.
+    Error,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Payload of a [`ReloadEvent::Custom`] event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Payload {
+    /// A plain string, which is passed to the page as-is.
+    Text(String),
+    /// A JSON value, which is passed to the page as a parsed object.
+    ///
+    /// Only available with the `json` feature enabled.
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+}
+
+impl Payload {
+    /// Create a new [`Payload::Text`] payload.
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Payload::Text(text.into())
+    }
+
+    /// Create a new [`Payload::Json`] payload.
+    ///
+    /// Only available with the `json` feature enabled.
+    #[cfg(feature = "json")]
+    pub fn json<V: Into<serde_json::Value>>(value: V) -> Self {
+        Payload::Json(value.into())
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{futures::OwnedNotified, Notify};
+
+use crate::{
+    event::{BuildStatus, ReloadEvent},
+    Client,
+};
+
+pub type Target = Arc<dyn Fn(&Client) -> bool + Send + Sync>;
+
+/// Bounded log of recently sent events, each tagged with a generation id.
+///
+/// Generation ids start at one and increase with each sent event, so that a
+/// generation of zero means that no events have been sent yet.
+#[derive(Debug)]
+pub struct History {
+    notify: Arc<Notify>,
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct Entries {
+    latest: u64,
+    lost: u64,
+    events: VecDeque<Entry>,
+    /// Log messages, which are kept apart from other events, as they are
+    /// neither replayed nor debounced.
+    logs: VecDeque<Entry>,
+    logged: u64,
+    paused: bool,
+    held: bool,
+    build: Option<BuildStatus>,
+}
+
+struct Entry {
+    id: u64,
+    message: Message,
+    target: Option<Target>,
+}
+
+/// Message sent to clients, either an event sent by users or a change of the
+/// internal state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Event(ReloadEvent),
+    Paused(bool),
+}
+
+impl std::fmt::Debug for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("id", &self.id)
+            .field("message", &self.message)
+            .field("target", &self.target.as_ref().map(|_| "..."))
+            .finish()
+    }
+}
+
+/// Result of looking up the events following some generation.
+pub enum Since {
+    /// Events addressed to each of the clients, in the order the clients were
+    /// given, along with the most recent generation.
+    Events(Vec<Vec<(u64, Message)>>, u64),
+    Missed(u64),
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            notify: Arc::new(Notify::new()),
+            entries: Mutex::new(Entries {
+                latest: 0,
+                lost: 0,
+                events: VecDeque::with_capacity(capacity),
+                logs: VecDeque::with_capacity(capacity),
+                logged: 0,
+                paused: false,
+                held: false,
+                build: None,
+            }),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, event: ReloadEvent, target: Option<Target>) {
+        let mut entries = self.entries.lock().unwrap();
+        if let ReloadEvent::Log(_) = event {
+            entries.logged += 1;
+            let id = entries.logged;
+            if entries.logs.len() == self.capacity {
+                entries.logs.pop_front();
+            }
+            entries.logs.push_back(Entry {
+                id,
+                message: Message::Event(event),
+                target,
+            });
+            drop(entries);
+
+            self.notify.notify_waiters();
+            return;
+        }
+
+        if let ReloadEvent::Build(status) = &event {
+            entries.held |= entries.paused && *status == BuildStatus::Succeeded;
+            entries.build = Some(status.clone()).filter(|status| *status != BuildStatus::Succeeded);
+        }
+
+        match event {
+            ReloadEvent::Reload
+            | ReloadEvent::Stylesheet(_)
+            | ReloadEvent::Asset(_)
+            | ReloadEvent::Module(_)
+                if entries.paused =>
+            {
+                entries.held = true;
+            }
+            event => self.append(&mut entries, Message::Event(event), target),
+        }
+        drop(entries);
+
+        self.notify.notify_waiters();
+    }
+
+    /// Pause or resume delivery of reloads.
+    ///
+    /// While paused, reloads and updates are held back. Once resumed, a single
+    /// reload is sent if anything has been held back in the meantime.
+    pub fn set_paused(&self, paused: bool) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.paused == paused {
+            return;
+        }
+
+        entries.paused = paused;
+        self.append(&mut entries, Message::Paused(paused), None);
+        if !paused && std::mem::take(&mut entries.held) {
+            self.append(&mut entries, Message::Event(ReloadEvent::Reload), None);
+        }
+        drop(entries);
+
+        self.notify.notify_waiters();
+    }
+
+    pub fn paused(&self) -> bool {
+        self.entries.lock().unwrap().paused
+    }
+
+    /// Return the status of the current build, unless it has succeeded.
+    pub fn build(&self) -> Option<BuildStatus> {
+        self.entries.lock().unwrap().build.clone()
+    }
+
+    fn append(&self, entries: &mut Entries, message: Message, target: Option<Target>) {
+        entries.latest += 1;
+        let id = entries.latest;
+        if entries.events.len() == self.capacity {
+            if let Some(evicted) = entries.events.pop_front() {
+                entries.lost = evicted.id;
+            }
+        }
+        entries.events.push_back(Entry {
+            id,
+            message,
+            target,
+        });
+    }
+
+    /// Return the generation of the most recently sent event.
+    pub fn latest(&self) -> u64 {
+        self.entries.lock().unwrap().latest
+    }
+
+    /// Return all events sent to each of the given clients after the given
+    /// generation.
+    ///
+    /// If some of these events have already been evicted from the log,
+    /// [`Since::Missed`] is returned with the most recent generation instead.
+    pub fn since(&self, after: u64, clients: &[&Client]) -> Since {
+        let entries = self.entries.lock().unwrap();
+        if after < entries.lost {
+            return Since::Missed(entries.latest);
+        }
+
+        let latest = entries.latest;
+        let candidates = newer(&entries.events, after);
+        // Targets are user code, which must not run while the lock is held.
+        drop(entries);
+
+        Since::Events(addressed(candidates, clients), latest)
+    }
+
+    /// Return the number of log messages sent so far.
+    pub fn logged(&self) -> u64 {
+        self.entries.lock().unwrap().logged
+    }
+
+    /// Return all log messages sent to each of the given clients after the
+    /// given number of log messages, along with the number of log messages
+    /// sent so far.
+    ///
+    /// Log messages that have already been evicted are skipped silently.
+    pub fn logs_since(&self, after: u64, clients: &[&Client]) -> (Vec<Vec<(u64, Message)>>, u64) {
+        let entries = self.entries.lock().unwrap();
+        let logged = entries.logged;
+        let candidates = newer(&entries.logs, after);
+        drop(entries);
+
+        (addressed(candidates, clients), logged)
+    }
+
+    /// Return a future that completes once new events have been sent.
+    ///
+    /// The future observes all events that are sent after it has been created,
+    /// even before it is polled for the first time.
+    pub fn notified(&self) -> OwnedNotified {
+        self.notify.clone().notified_owned()
+    }
+}
+
+type Candidate = (u64, Message, Option<Target>);
+
+fn newer(entries: &VecDeque<Entry>, after: u64) -> Vec<Candidate> {
+    entries
+        .iter()
+        .filter(|entry| entry.id > after)
+        .map(|entry| (entry.id, entry.message.clone(), entry.target.clone()))
+        .collect()
+}
+
+fn addressed(candidates: Vec<Candidate>, clients: &[&Client]) -> Vec<Vec<(u64, Message)>> {
+    clients
+        .iter()
+        .map(|client| {
+            candidates
+                .iter()
+                .filter(|(_, _, target)| target.as_ref().map_or(true, |target| target(client)))
+                .map(|(id, message, _)| (*id, message.clone()))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{LogLevel, LogRecord};
+
+    fn events(since: Since) -> Vec<(u64, Message)> {
+        match since {
+            Since::Events(mut events, _) => events.remove(0),
+            Since::Missed(latest) => panic!("missed events up to {}", latest),
+        }
+    }
+
+    #[test]
+    fn since_returns_newer_events() {
+        let history = History::new(8);
+        let client = Client::test("/", &[]);
+        history.push(ReloadEvent::Stylesheet("/app.css".to_owned()), None);
+        history.push(ReloadEvent::Reload, None);
+
+        assert_eq!(history.latest(), 2);
+        assert_eq!(
+            events(history.since(0, &[&client])),
+            vec![
+                (
+                    1,
+                    Message::Event(ReloadEvent::Stylesheet("/app.css".to_owned()))
+                ),
+                (2, Message::Event(ReloadEvent::Reload)),
+            ]
+        );
+        assert_eq!(
+            events(history.since(1, &[&client])),
+            vec![(2, Message::Event(ReloadEvent::Reload))]
+        );
+        assert_eq!(events(history.since(2, &[&client])), vec![]);
+    }
+
+    #[test]
+    fn since_filters_targets() {
+        let history = History::new(8);
+        let target: Target = Arc::new(|client: &Client| client.path() == "/docs/");
+        history.push(ReloadEvent::Reload, Some(target));
+
+        assert_eq!(events(history.since(0, &[&Client::test("/", &[])])), vec![]);
+        assert_eq!(
+            events(history.since(0, &[&Client::test("/docs/", &[])])),
+            vec![(1, Message::Event(ReloadEvent::Reload))]
+        );
+    }
+
+    #[test]
+    fn since_filters_targets_for_each_client() {
+        let history = History::new(8);
+        let target: Target = Arc::new(|client: &Client| client.path() == "/docs/");
+        history.push(ReloadEvent::Reload, Some(target));
+        history.push(ReloadEvent::Asset("/logo.png".to_owned()), None);
+
+        let Since::Events(events, 2) =
+            history.since(0, &[&Client::test("/", &[]), &Client::test("/docs/", &[])])
+        else {
+            panic!("missed events");
+        };
+        let asset = (
+            2,
+            Message::Event(ReloadEvent::Asset("/logo.png".to_owned())),
+        );
+        assert_eq!(
+            events,
+            vec![
+                vec![asset.clone()],
+                vec![(1, Message::Event(ReloadEvent::Reload)), asset],
+            ]
+        );
+    }
+
+    #[test]
+    fn since_runs_targets_without_lock() {
+        let history = Arc::new(History::new(8));
+        let target: Target = Arc::new({
+            let history = Arc::downgrade(&history);
+            move |_: &Client| {
+                history
+                    .upgrade()
+                    .is_some_and(|history| history.latest() > 0)
+            }
+        });
+        history.push(ReloadEvent::Reload, Some(target));
+
+        assert_eq!(
+            events(history.since(0, &[&Client::test("/", &[])])).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn since_reports_evicted_events() {
+        let history = History::new(2);
+        let client = Client::test("/", &[]);
+        for _ in 0..3 {
+            history.push(ReloadEvent::Reload, None);
+        }
+
+        assert!(matches!(history.since(0, &[&client]), Since::Missed(3)));
+        assert_eq!(events(history.since(1, &[&client])).len(), 2);
+    }
+
+    #[test]
+    fn logs_are_kept_apart() {
+        let history = History::new(2);
+        let client = Client::test("/", &[]);
+        history.push(ReloadEvent::Reload, None);
+        for message in ["first", "second", "third"] {
+            history.push(
+                ReloadEvent::Log(LogRecord {
+                    level: LogLevel::Info,
+                    target: "app".to_owned(),
+                    message: message.to_owned(),
+                }),
+                None,
+            );
+        }
+
+        assert_eq!(history.latest(), 1);
+        assert_eq!(history.logged(), 3);
+        assert_eq!(events(history.since(0, &[&client])).len(), 1);
+
+        let (logs, logged) = history.logs_since(0, &[&client]);
+        assert_eq!(logged, 3);
+        assert_eq!(logs[0].len(), 2);
+        assert_eq!(history.logs_since(3, &[&client]).0, vec![vec![]]);
+    }
+
+    #[test]
+    fn pause_holds_reloads() {
+        let history = History::new(8);
+        let client = Client::test("/", &[]);
+        history.set_paused(true);
+        history.push(ReloadEvent::Reload, None);
+        history.push(ReloadEvent::Asset("/logo.png".to_owned()), None);
+        history.set_paused(false);
+
+        assert_eq!(
+            events(history.since(0, &[&client])),
+            vec![
+                (1, Message::Paused(true)),
+                (2, Message::Paused(false)),
+                (3, Message::Event(ReloadEvent::Reload)),
+            ]
+        );
+    }
+}
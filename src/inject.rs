@@ -1,32 +1,55 @@
 use std::{
     future::Future,
+    sync::Arc,
     task::{ready, Poll},
 };
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 use http::{header, Request, Response};
 use http_body::Frame;
 use tower::Service;
 
-use crate::predicate::Predicate;
+use crate::{history::History, predicate::Predicate};
+
+/// Header sent by clients to fetch pages without injected live-reload logic.
+pub const NO_INJECT_HEADER: &str = "x-tower-livereload-no-inject";
 
 #[derive(Clone, Debug)]
 pub struct InjectService<S, ReqPred, ResPred> {
     service: S,
+    /// Attributes and contents of the injected script tag.
     data: Bytes,
+    history: Arc<History>,
     req_predicate: ReqPred,
     res_predicate: ResPred,
 }
 
 impl<S, ReqPred, ResPred> InjectService<S, ReqPred, ResPred> {
-    pub fn new(service: S, data: Bytes, req_predicate: ReqPred, res_predicate: ResPred) -> Self {
+    pub fn new(
+        service: S,
+        data: Bytes,
+        history: Arc<History>,
+        req_predicate: ReqPred,
+        res_predicate: ResPred,
+    ) -> Self {
         Self {
             service,
             data,
+            history,
             req_predicate,
             res_predicate,
         }
     }
+
+    /// Complete the script tag with the current generation, so that pages can
+    /// pick up events that are sent before they connect to the server.
+    fn script(&self) -> Bytes {
+        let mut script = BytesMut::from(
+            format!(r#"<script data-last-event-id="{}""#, self.history.latest()).as_bytes(),
+        );
+        script.extend_from_slice(&self.data);
+        script.freeze()
+    }
 }
 
 impl<S, ReqPred, ResPred, ReqBody, ResBody> Service<Request<ReqBody>>
@@ -46,10 +69,11 @@ where
     }
 
     fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
-        let should_inject = self.req_predicate.check(&request);
+        let should_inject =
+            !request.headers().contains_key(NO_INJECT_HEADER) && self.req_predicate.check(&request);
         InjectResponseFuture {
             inner: self.service.call(request),
-            data: should_inject.then(|| self.data.clone()),
+            data: should_inject.then(|| self.script()),
             predicate: self.res_predicate,
         }
     }
@@ -38,8 +38,37 @@
 //! entirely using hooks from Rust code. See this [example] on GitHub for
 //! pointers on how to implement a self-contained live-reloading static server.
 //!
+//! Besides full page reloads, [`Reloader::send`] can also send finer-grained
+//! events to the browser, such as stylesheet updates. See
+//! [`event::ReloadEvent`] for all supported kinds of events.
+//!
 //! [example]: https://github.com/leotaku/tower-livereload/blob/master/examples/axum-file-watch/
 //!
+//! # Optional features
+//!
+//! With the `control` feature enabled, the [`control`] module provides a
+//! local socket that accepts simple text commands such as `reload` or
+//! `css /static/app.css`. This allows build tools written in any language to
+//! trigger browser reloads.
+//!
+//! [`control`]: https://docs.rs/tower-livereload/latest/tower_livereload/control/index.html
+//!
+//! Similarly, the `cargo` feature enables the [`cargo`] module, which forwards
+//! compiler errors reported by `cargo build --message-format=json` to the
+//! browser, where they are displayed in an overlay.
+//!
+//! [`cargo`]: https://docs.rs/tower-livereload/latest/tower_livereload/cargo/index.html
+//!
+//! The `tracing` feature enables the [`console`] module, which forwards server
+//! logs to the browser console of connected pages.
+//!
+//! [`console`]: https://docs.rs/tower-livereload/latest/tower_livereload/console/index.html
+//!
+//! The `json` feature enables [`Payload::json`], which sends a JSON value
+//! as the payload of a custom event.
+//!
+//! [`Payload::json`]: https://docs.rs/tower-livereload/latest/tower_livereload/event/enum.Payload.html#method.json
+//!
 //! # Ecosystem compatibility
 //!
 //! `tower-livereload` has been built from the ground up to provide the highest
@@ -76,30 +105,60 @@
 #![warn(clippy::all, missing_docs, nonstandard_style, future_incompatible)]
 #![allow(clippy::type_complexity)]
 
+mod ack;
+mod body;
+pub mod browser;
+#[cfg(feature = "cargo")]
+pub mod cargo;
+mod command;
+#[cfg(feature = "tracing")]
+pub mod console;
+#[cfg(feature = "control")]
+pub mod control;
+pub mod event;
+mod history;
 mod inject;
 mod overlay;
 pub mod predicate;
+mod query;
+mod registry;
 mod sse;
 
-use std::{convert::Infallible, sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use http::{header, Request, Response, StatusCode};
-use tokio::sync::Notify;
+use http::{header, Method, Request, Response, StatusCode};
 use tower::{Layer, Service};
 
 use crate::{
-    inject::InjectService,
-    overlay::OverlayService,
+    ack::Acknowledgements,
+    body::InternalBody,
+    browser::BrowserLog,
+    command::Command,
+    event::{BuildStatus, ReloadEvent},
+    history::{History, Target},
+    inject::{InjectService, NO_INJECT_HEADER},
+    overlay::{Alternative, OverlayService},
     predicate::{Always, ContentTypeStartsWith, Predicate},
-    sse::ReloadEventsBody,
+    registry::Registry,
+    sse::{instance_id, ReloadEventsBody},
 };
 
 const DEFAULT_PREFIX: &str = "/_tower-livereload";
+const HISTORY_CAPACITY: usize = 64;
 
 /// Utility to send reload requests to clients.
 #[derive(Clone, Debug)]
 pub struct Reloader {
-    sender: Arc<Notify>,
+    history: Arc<History>,
+    registry: Arc<Registry>,
+    acks: Arc<Acknowledgements>,
+    channel: Option<Arc<str>>,
 }
 
 impl Reloader {
@@ -108,15 +167,225 @@ impl Reloader {
     /// A standalone [`Reloader`] is not useful in most cases. Instead, the
     /// [`LiveReloadLayer::reloader`] utility should be used to create a
     /// [`Reloader`] that can send reload requests to connected clients.
+    /// Alternatively, the created [`Reloader`] can be passed to one or more
+    /// layers using [`LiveReloadLayer::with_reloader`].
     pub fn new() -> Self {
         Self {
-            sender: Arc::new(Notify::new()),
+            history: Arc::new(History::new(HISTORY_CAPACITY)),
+            registry: Arc::new(Registry::default()),
+            acks: Arc::new(Acknowledgements::default()),
+            channel: None,
         }
     }
 
     /// Send a reload request to all open clients.
     pub fn reload(&self) {
-        self.sender.notify_waiters();
+        self.send(ReloadEvent::Reload);
+    }
+
+    /// Send a reload request to all open clients, then wait until they have
+    /// finished reloading.
+    ///
+    /// Clients acknowledge a reload once their reloaded page has been shown.
+    /// Any client that was connected when this function was called but has not
+    /// acknowledged the reload before the given timeout is counted as timed
+    /// out. Each page is counted at most once, no matter how often it reloads,
+    /// and pages that connected later are not counted at all.
+    pub async fn reload_and_wait(&self, timeout: Duration) -> ReloadAcknowledgement {
+        let channel = self.channel.as_deref();
+        let expected = self
+            .registry
+            .clients()
+            .into_iter()
+            .filter(|client| client.subscribed(channel))
+            .filter_map(|client| client.token)
+            .collect::<HashSet<_>>();
+        let expected_count = expected.len();
+        let acknowledged = self
+            .acks
+            .wait(self.history.latest(), expected, timeout, || self.reload())
+            .await;
+
+        ReloadAcknowledgement {
+            acknowledged,
+            timed_out: expected_count - acknowledged,
+        }
+    }
+
+    /// Send the given event to all open clients.
+    ///
+    /// See [`ReloadEvent`] for the kinds of events that are supported.
+    ///
+    /// Recently sent events are retained, so that clients which are briefly
+    /// disconnected when an event is sent still receive it once they
+    /// reconnect.
+    pub fn send(&self, event: ReloadEvent) {
+        self.push(event, None);
+    }
+
+    /// Send a reload request to all open clients whose page path matches the
+    /// given pattern.
+    ///
+    /// Patterns are matched against the whole path, where `*` matches any
+    /// sequence of characters. For example, `/docs/*` matches all pages below
+    /// `/docs/`, while `/docs` only matches that exact page.
+    pub fn reload_matching<P: Into<String>>(&self, pattern: P) {
+        let pattern = pattern.into();
+        self.reload_where(move |client| glob_match(&pattern, client.path()));
+    }
+
+    /// Send a reload request to all open clients that match the given
+    /// predicate.
+    pub fn reload_where<F>(&self, predicate: F)
+    where
+        F: Fn(&Client) -> bool + Send + Sync + 'static,
+    {
+        self.send_where(ReloadEvent::Reload, predicate);
+    }
+
+    /// Update stylesheets loaded from the given path, e.g. `/static/app.css`,
+    /// on all open clients without reloading the page.
+    ///
+    /// Clients re-fetch matching `<link rel="stylesheet">` elements and
+    /// `@import` rules, which keeps the state of the page intact. Clients that
+    /// have not loaded a matching stylesheet reload the whole page instead.
+    pub fn reload_stylesheet<P: Into<String>>(&self, path: P) {
+        self.send(ReloadEvent::Stylesheet(path.into()));
+    }
+
+    /// Update static assets loaded from the given path, e.g.
+    /// `/static/logo.svg`, on all open clients without reloading the page.
+    ///
+    /// Clients re-fetch matching images, including `srcset` candidates,
+    /// `<source>` elements of pictures and media, favicons, and CSS
+    /// `background-image` references. Clients that have not loaded a matching
+    /// asset reload the whole page instead.
+    pub fn reload_asset<P: Into<String>>(&self, path: P) {
+        self.send(ReloadEvent::Asset(path.into()));
+    }
+
+    /// Replace the JavaScript module loaded from the given path, e.g.
+    /// `/js/widget.js`, on all open clients without reloading the page.
+    ///
+    /// Modules opt into being replaced by registering callbacks with the
+    /// injected live-reload logic:
+    ///
+    /// ```js
+    /// const hot = window.towerLiveReload?.hot(import.meta.url);
+    /// hot?.dispose((data) => {
+    ///   // Clean up side effects of the old module, and store state in `data`.
+    /// });
+    /// hot?.accept((module) => {
+    ///   // Apply the updated module, whose previous `data` is available as
+    ///   // `hot.data` when it registers its own callbacks.
+    /// });
+    /// ```
+    ///
+    /// Clients call the `dispose` callbacks of the old module, re-import the
+    /// module with a cache-busting query parameter and call the `accept`
+    /// callbacks with the new module. Clients where no loaded module has
+    /// accepted updates reload the whole page instead.
+    pub fn reload_module<P: Into<String>>(&self, path: P) {
+        self.send(ReloadEvent::Module(path.into()));
+    }
+
+    /// Send the given event to all open clients that match the given
+    /// predicate.
+    pub fn send_where<F>(&self, event: ReloadEvent, predicate: F)
+    where
+        F: Fn(&Client) -> bool + Send + Sync + 'static,
+    {
+        self.push(event, Some(Arc::new(predicate)));
+    }
+
+    /// Report that a build has started to all open clients.
+    ///
+    /// Clients display a small spinner until the build has finished.
+    pub fn build_started(&self) {
+        self.send(ReloadEvent::Build(BuildStatus::Started));
+    }
+
+    /// Report that a build has failed to all open clients.
+    ///
+    /// Clients display the given error message in an overlay, which remains
+    /// visible until the next build succeeds or it is dismissed.
+    pub fn build_failed<M: Into<String>>(&self, message: M) {
+        self.send(ReloadEvent::Build(BuildStatus::Failed(message.into())));
+    }
+
+    /// Report that a build has succeeded to all open clients, which causes
+    /// them to reload.
+    pub fn build_succeeded(&self) {
+        self.send(ReloadEvent::Build(BuildStatus::Succeeded));
+    }
+
+    /// Pause delivery of reloads to all clients.
+    ///
+    /// While paused, reloads as well as stylesheet, asset and module updates
+    /// are held back, and successful builds no longer reload the page. Custom
+    /// events and build failures are still delivered. Connected pages display
+    /// a notice while reloading is paused.
+    pub fn pause(&self) {
+        self.history.set_paused(true);
+    }
+
+    /// Resume delivery of reloads to all clients.
+    ///
+    /// If any reloads or updates have been held back while paused, a single
+    /// reload is sent to all clients.
+    pub fn resume(&self) {
+        self.history.set_paused(false);
+    }
+
+    /// Return whether delivery of reloads is currently paused.
+    pub fn is_paused(&self) -> bool {
+        self.history.paused()
+    }
+
+    /// Return a [`Reloader`] that sends events on the named channel.
+    ///
+    /// Events sent on a channel only reach clients that have subscribed to
+    /// that channel. Events sent by a [`Reloader`] that is not bound to a
+    /// channel always reach every client.
+    ///
+    /// Pages subscribe to channels either through
+    /// [`LiveReloadLayer::channels`], or by including a marker in their HTML
+    /// such as the following:
+    ///
+    /// ```html
+    /// <meta name="tower-livereload-channels" content="wasm templates">
+    /// ```
+    pub fn channel<N: AsRef<str>>(&self, name: N) -> Reloader {
+        Reloader {
+            channel: Some(name.as_ref().into()),
+            ..self.clone()
+        }
+    }
+
+    fn push(&self, event: ReloadEvent, target: Option<Target>) {
+        let target = match (self.channel.clone(), target) {
+            (None, target) => target,
+            (Some(channel), None) => {
+                Some(Arc::new(move |client: &Client| client.subscribed(Some(&channel))) as Target)
+            }
+            (Some(channel), Some(target)) => Some(Arc::new(move |client: &Client| {
+                client.subscribed(Some(&channel)) && target(client)
+            }) as Target),
+        };
+
+        self.history.push(event, target);
+    }
+
+    /// Return information about all currently connected clients.
+    pub fn clients(&self) -> Vec<Client> {
+        self.registry.clients()
+    }
+
+    /// Return the number of currently connected clients.
+    ///
+    /// This is useful to warn when a reload would not reach any browser.
+    pub fn client_count(&self) -> usize {
+        self.registry.len()
     }
 }
 
@@ -126,25 +395,283 @@ impl Default for Reloader {
     }
 }
 
+/// Outcome of [`Reloader::reload_and_wait`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReloadAcknowledgement {
+    /// Number of clients that reloaded their page in time.
+    pub acknowledged: usize,
+    /// Number of clients that did not reload their page in time.
+    pub timed_out: usize,
+}
+
+/// Strategy used by clients to apply reloads, see
+/// [`LiveReloadLayer::reload_strategy`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReloadStrategy {
+    /// Reload the entire page.
+    #[default]
+    Reload,
+    /// Fetch the page again and merge the changes into the current document,
+    /// keeping unchanged elements intact.
+    Morph,
+}
+
+/// Information about a client connected to the live-reload event stream.
+#[derive(Clone, Debug)]
+pub struct Client {
+    path: String,
+    url: String,
+    user_agent: Option<String>,
+    remote_addr: Option<SocketAddr>,
+    connected_at: SystemTime,
+    channels: Vec<String>,
+    /// Token identifying the page across reloads, used for acknowledgements.
+    token: Option<String>,
+}
+
+impl Client {
+    /// Return the URL path of the page the client has loaded, e.g. `/docs/`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return the full URL of the page the client has loaded.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the [`User-Agent`] reported by the client, if any.
+    ///
+    /// [`User-Agent`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/User-Agent
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Return the remote address of the client, if known.
+    ///
+    /// By default, the remote address is only known if the server inserts it
+    /// into the request extensions as a [`SocketAddr`]. See
+    /// [`LiveReloadLayer::remote_addr`] for other servers.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Return the time at which the client connected.
+    ///
+    /// Note that clients reconnect to the event stream after network errors,
+    /// which resets this time.
+    pub fn connected_at(&self) -> SystemTime {
+        self.connected_at
+    }
+
+    /// Return the channels the client has subscribed to.
+    ///
+    /// See [`Reloader::channel`] for more information on channels.
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Read the client from the query parameters of a request to the event
+    /// stream, or of a request sharing the event stream of another client.
+    fn from_request(parts: &http::request::Parts, remote_addr: &RemoteAddr) -> Self {
+        let path = query::get(&parts.uri, "path").unwrap_or_default();
+        Client {
+            url: query::get(&parts.uri, "url").unwrap_or_else(|| path.clone()),
+            path,
+            user_agent: parts
+                .headers
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok().map(ToOwned::to_owned)),
+            remote_addr: (remote_addr.0)(&parts.extensions),
+            connected_at: SystemTime::now(),
+            channels: query::get(&parts.uri, "channels")
+                .map(|channels| {
+                    channels
+                        .split(',')
+                        .filter(|channel| !channel.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            token: query::get(&parts.uri, "client").filter(|token| !token.is_empty()),
+        }
+    }
+
+    fn subscribed(&self, channel: Option<&str>) -> bool {
+        match channel {
+            Some(channel) => self.channels.iter().any(|other| other == channel),
+            None => true,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn test(path: &str, channels: &[&str]) -> Self {
+        Client {
+            path: path.to_owned(),
+            url: format!("http://localhost{}", path),
+            user_agent: None,
+            remote_addr: None,
+            connected_at: SystemTime::UNIX_EPOCH,
+            channels: channels.iter().map(|channel| channel.to_string()).collect(),
+            token: None,
+        }
+    }
+}
+
+fn text(status: StatusCode, body: Option<String>) -> Result<Response<InternalBody>, Infallible> {
+    let mut builder = Response::builder().status(status);
+    if body.is_some() {
+        builder = builder.header(header::CONTENT_TYPE, "text/plain; charset=utf-8");
+    }
+
+    builder
+        .body(InternalBody::Full(
+            body.map(|body| format!("{}\n", body).into()),
+        ))
+        .map_err(|_| unreachable!())
+}
+
+fn method_not_allowed() -> Result<Response<InternalBody>, Infallible> {
+    Response::builder()
+        .status(StatusCode::METHOD_NOT_ALLOWED)
+        .header(header::ALLOW, "POST")
+        .body(InternalBody::empty())
+        .map_err(|_| unreachable!())
+}
+
+fn cross_origin() -> Result<Response<InternalBody>, Infallible> {
+    text(
+        StatusCode::FORBIDDEN,
+        Some("error: cross-origin requests are not allowed".to_owned()),
+    )
+}
+
+/// Check whether a request was sent from the origin of the server itself, or
+/// by a client other than a browser, which does not send an [`Origin`] header.
+///
+/// [`Origin`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Origin
+fn same_origin(parts: &http::request::Parts) -> bool {
+    let Some(origin) = parts.headers.get(header::ORIGIN) else {
+        return true;
+    };
+
+    let host = parts
+        .headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| parts.uri.authority().map(|authority| authority.as_str()));
+    let origin = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, authority)| authority);
+    origin.is_some() && origin == host
+}
+
+/// Function registered using [`LiveReloadLayer::remote_addr`].
+#[derive(Clone)]
+struct RemoteAddr(Arc<dyn Fn(&http::Extensions) -> Option<SocketAddr> + Send + Sync>);
+
+impl std::fmt::Debug for RemoteAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RemoteAddr").field(&"...").finish()
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+fn glob_match(pattern: &str, path: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == path,
+        Some((prefix, rest)) => {
+            path.starts_with(prefix)
+                && path[prefix.len()..]
+                    .char_indices()
+                    .map(|(index, _)| index)
+                    .chain([path.len() - prefix.len()])
+                    .any(|index| glob_match(rest, &path[prefix.len() + index..]))
+        }
+    }
+}
+
 /// Layer to apply [`LiveReload`] middleware.
 #[derive(Clone, Debug)]
 pub struct LiveReloadLayer<ReqPred = Always, ResPred = ContentTypeStartsWith<&'static str>> {
-    custom_prefix: Option<String>,
     reloader: Reloader,
     req_predicate: ReqPred,
     res_predicate: ResPred,
+    options: Options,
+}
+
+#[derive(Clone, Debug)]
+struct Options {
+    custom_prefix: Option<String>,
     reload_interval: Duration,
+    debounce: Option<Duration>,
+    channels: Vec<String>,
+    trigger: bool,
+    trigger_token: Option<String>,
+    on_browser_log: Option<browser::Handler>,
+    capture_console: bool,
+    reload_strategy: ReloadStrategy,
+    preserve_scroll: bool,
+    preserve_forms: bool,
+    preserve_passwords: bool,
+    defer_hidden_reloads: bool,
+    share_connection: bool,
+    remote_addr: RemoteAddr,
 }
 
 impl LiveReloadLayer {
     /// Create a new [`LiveReloadLayer`] with default settings.
     pub fn new() -> Self {
+        Self::with_reloader(Reloader::new())
+    }
+
+    /// Create a new [`LiveReloadLayer`] with default settings, which uses the
+    /// given [`Reloader`].
+    ///
+    /// This allows sharing a single [`Reloader`] between multiple layers, e.g.
+    /// when multiple routers with different settings are driven by the same
+    /// file watcher. Reload requests sent by the [`Reloader`] then reach the
+    /// clients of all these layers.
+    ///
+    /// Note that when several layers use the same prefix, the event stream of
+    /// all their pages is served by the outermost layer. Options of the event
+    /// stream, namely [`LiveReloadLayer::reload_interval`] and
+    /// [`LiveReloadLayer::debounce`], are then taken from the outermost layer
+    /// only. Use [`LiveReloadLayer::custom_prefix`] to give layers with
+    /// different options their own event stream.
+    pub fn with_reloader(reloader: Reloader) -> Self {
         Self {
-            custom_prefix: None,
-            reloader: Reloader::new(),
+            reloader,
             req_predicate: Always,
             res_predicate: ContentTypeStartsWith::new("text/html"),
-            reload_interval: Duration::from_secs(1),
+            options: Options {
+                custom_prefix: None,
+                reload_interval: Duration::from_secs(1),
+                debounce: None,
+                channels: Vec::new(),
+                trigger: false,
+                trigger_token: None,
+                on_browser_log: None,
+                capture_console: false,
+                reload_strategy: ReloadStrategy::Reload,
+                preserve_scroll: false,
+                preserve_forms: false,
+                preserve_passwords: false,
+                defer_hidden_reloads: false,
+                share_connection: false,
+                remote_addr: RemoteAddr(Arc::new(|extensions| {
+                    extensions.get::<SocketAddr>().copied()
+                })),
+            },
         }
     }
 }
@@ -157,7 +684,10 @@ impl<ReqPred, ResPred> LiveReloadLayer<ReqPred, ResPred> {
     /// such, it has to include a leading slash to match URL paths correctly.
     pub fn custom_prefix<P: Into<String>>(self, prefix: P) -> Self {
         Self {
-            custom_prefix: Some(prefix.into()),
+            options: Options {
+                custom_prefix: Some(prefix.into()),
+                ..self.options
+            },
             ..self
         }
     }
@@ -175,11 +705,10 @@ impl<ReqPred, ResPred> LiveReloadLayer<ReqPred, ResPred> {
         predicate: P,
     ) -> LiveReloadLayer<P, ResPred> {
         LiveReloadLayer {
-            custom_prefix: self.custom_prefix,
             reloader: self.reloader,
             req_predicate: predicate,
             res_predicate: self.res_predicate,
-            reload_interval: self.reload_interval,
+            options: self.options,
         }
     }
 
@@ -200,18 +729,309 @@ impl<ReqPred, ResPred> LiveReloadLayer<ReqPred, ResPred> {
         predicate: P,
     ) -> LiveReloadLayer<ReqPred, P> {
         LiveReloadLayer {
-            custom_prefix: self.custom_prefix,
             reloader: self.reloader,
             req_predicate: self.req_predicate,
             res_predicate: predicate,
-            reload_interval: self.reload_interval,
+            options: self.options,
         }
     }
 
     /// Set a custom retry interval for the live-reload logic.
+    ///
+    /// When several layers share a prefix, only the interval of the outermost
+    /// layer is used, see [`LiveReloadLayer::with_reloader`].
     pub fn reload_interval(self, interval: Duration) -> Self {
         Self {
-            reload_interval: interval,
+            options: Options {
+                reload_interval: interval,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Delay reloads sent to clients until no further reloads have been sent
+    /// for the given duration.
+    ///
+    /// This is useful when reloads are triggered by file changes, as editors
+    /// and build tools often write many files in short bursts. All reloads as
+    /// well as stylesheet, asset and module updates sent during such a burst
+    /// are merged, so that each client reloads at most once. Other events,
+    /// such as build status updates and custom events, are sent right away.
+    ///
+    /// Note that debouncing relies on the timer of the [`tokio`] runtime, so
+    /// the runtime serving requests needs to have its time driver enabled.
+    /// When several layers share a prefix, only the delay of the outermost
+    /// layer is used, see [`LiveReloadLayer::with_reloader`].
+    ///
+    /// [`tokio`]: https://docs.rs/tokio
+    pub fn debounce(self, delay: Duration) -> Self {
+        Self {
+            options: Options {
+                debounce: Some(delay),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Subscribe pages injected by the given [`LiveReloadLayer`] to the given
+    /// channels.
+    ///
+    /// To subscribe only some pages to a channel, apply separate layers
+    /// created using [`LiveReloadLayer::with_reloader`] to the respective
+    /// routes. See [`Reloader::channel`] for more information on channels.
+    pub fn channels<I, C>(self, channels: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<String>,
+    {
+        Self {
+            options: Options {
+                channels: channels.into_iter().map(Into::into).collect(),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Enable or disable the trigger route.
+    ///
+    /// The trigger route is disabled by default, as it allows anyone who can
+    /// reach the server to send events to connected clients. See
+    /// [`LiveReload`] for more information on the trigger route.
+    pub fn trigger(self, enabled: bool) -> Self {
+        Self {
+            options: Options {
+                trigger: enabled,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Require the given token for requests to the trigger route.
+    ///
+    /// The token has to be passed either as a bearer token in the
+    /// [`Authorization`] header, or using the `token` query parameter. Note
+    /// that the trigger route has to be enabled using
+    /// [`LiveReloadLayer::trigger`] as well.
+    ///
+    /// [`Authorization`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Authorization
+    pub fn trigger_token<T: Into<String>>(self, token: T) -> Self {
+        Self {
+            options: Options {
+                trigger_token: Some(token.into()),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Set the strategy used by clients to apply reloads.
+    ///
+    /// By default, clients reload the entire page, which loses state such as
+    /// the scroll position, focus, open `<details>` elements and the state of
+    /// scripts. Using [`ReloadStrategy::Morph`], clients instead fetch the
+    /// page in the background and only update the parts of the document that
+    /// have changed.
+    ///
+    /// Morphing does not run scripts of the fetched page. When the scripts in
+    /// the `<head>` of the page have changed, or the page cannot be fetched,
+    /// clients fall back to reloading the entire page. Clients also always
+    /// reload the entire page when the server has been restarted.
+    pub fn reload_strategy(self, strategy: ReloadStrategy) -> Self {
+        Self {
+            options: Options {
+                reload_strategy: strategy,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Restore scroll positions after clients have reloaded the page.
+    ///
+    /// Before reloading, clients store the scroll position of the window and
+    /// of all scrolled elements that have an `id` attribute in
+    /// [`sessionStorage`], and restore them once the page has loaded again.
+    /// This is disabled by default.
+    ///
+    /// [`sessionStorage`]: https://developer.mozilla.org/en-US/docs/Web/API/Window/sessionStorage
+    pub fn preserve_scroll(self, preserve: bool) -> Self {
+        Self {
+            options: Options {
+                preserve_scroll: preserve,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Restore the values of form fields after clients have reloaded the
+    /// page.
+    ///
+    /// Before reloading, clients store the values of all `<input>`,
+    /// `<textarea>` and `<select>` elements in [`sessionStorage`], keyed by
+    /// their `name` or `id` attribute. Once the page has loaded again, values
+    /// are restored and `input` as well as `change` events are dispatched, so
+    /// that frameworks pick up the restored values. This is disabled by
+    /// default.
+    ///
+    /// Password fields are excluded, unless enabled using
+    /// [`LiveReloadLayer::preserve_passwords`].
+    ///
+    /// [`sessionStorage`]: https://developer.mozilla.org/en-US/docs/Web/API/Window/sessionStorage
+    pub fn preserve_forms(self, preserve: bool) -> Self {
+        Self {
+            options: Options {
+                preserve_forms: preserve,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Also restore the values of password fields when form values are
+    /// preserved using [`LiveReloadLayer::preserve_forms`].
+    ///
+    /// This is disabled by default, as it stores passwords in
+    /// [`sessionStorage`] in plain text.
+    ///
+    /// [`sessionStorage`]: https://developer.mozilla.org/en-US/docs/Web/API/Window/sessionStorage
+    pub fn preserve_passwords(self, preserve: bool) -> Self {
+        Self {
+            options: Options {
+                preserve_passwords: preserve,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Defer reloads of pages in hidden tabs until they become visible again.
+    ///
+    /// By default, all clients reload as soon as a reload is sent, which can
+    /// put a lot of load on the server when many tabs are open. With this
+    /// option enabled, clients whose page is hidden only remember that they
+    /// are out of date, and reload once the page is shown again. Stylesheet
+    /// and asset updates are applied immediately regardless.
+    ///
+    /// Note that [`Reloader::reload_and_wait`] counts deferred reloads as timed
+    /// out, unless the page becomes visible before the timeout.
+    pub fn defer_hidden_reloads(self, defer: bool) -> Self {
+        Self {
+            options: Options {
+                defer_hidden_reloads: defer,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Share a single connection to the event stream between all pages of a
+    /// browser.
+    ///
+    /// By default, every page opens its own connection. Over HTTP/1.1,
+    /// browsers limit the number of connections per origin to about six, so
+    /// that pages stop loading once too many tabs are open. With this option
+    /// enabled, all pages of an origin that use the same event stream elect a
+    /// single page that holds the connection and forwards events to the other
+    /// pages using a [`BroadcastChannel`]. Browsers without support for the
+    /// [Web Locks API] fall back to one connection per page.
+    ///
+    /// The other pages register themselves with the server, which tags each
+    /// event with the pages it is addressed to. As such, events sent using
+    /// [`Reloader::reload_matching`], [`Reloader::send_where`] or
+    /// [`Reloader::channel`] still only reach matching pages,
+    /// [`Reloader::clients`] lists every page, and
+    /// [`Reloader::reload_and_wait`] waits for every page.
+    ///
+    /// [`BroadcastChannel`]: https://developer.mozilla.org/en-US/docs/Web/API/BroadcastChannel
+    /// [Web Locks API]: https://developer.mozilla.org/en-US/docs/Web/API/Web_Locks_API
+    pub fn share_connection(self, share: bool) -> Self {
+        Self {
+            options: Options {
+                share_connection: share,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Call the given function with errors reported by injected pages.
+    ///
+    /// Once a handler has been registered, injected pages report uncaught
+    /// errors, unhandled promise rejections and resources that failed to load
+    /// to `POST <prefix>/log`. This makes errors that occur on other devices,
+    /// such as phones used for testing, visible on the server. Reports sent by
+    /// pages from other origins are rejected. See [`browser`] for more
+    /// information.
+    ///
+    /// Note that the handler is called while serving the request, and as such
+    /// should not block.
+    pub fn on_browser_log<F>(self, handler: F) -> Self
+    where
+        F: Fn(BrowserLog) + Send + Sync + 'static,
+    {
+        Self {
+            options: Options {
+                on_browser_log: Some(browser::Handler(Arc::new(handler))),
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Also report calls to `console.*` methods, such as `console.log` or
+    /// `console.warn`, to the handler registered using
+    /// [`LiveReloadLayer::on_browser_log`].
+    ///
+    /// This is disabled by default, as it may result in a large number of
+    /// requests for pages that log a lot.
+    pub fn capture_console(self, capture: bool) -> Self {
+        Self {
+            options: Options {
+                capture_console: capture,
+                ..self.options
+            },
+            ..self
+        }
+    }
+
+    /// Set a custom function to look up the remote address of clients in the
+    /// request extensions.
+    ///
+    /// By default, the remote address is read from a [`SocketAddr`] in the
+    /// request extensions, if any. Servers that store it differently can
+    /// provide their own lookup, which is used for [`Client::remote_addr`] and
+    /// [`BrowserLog::remote_addr`]. For example, [`axum`] stores the remote
+    /// address as `ConnectInfo<SocketAddr>` when serving an app using
+    /// `into_make_service_with_connect_info::<SocketAddr>()`:
+    ///
+    /// ```
+    /// use std::net::SocketAddr;
+    ///
+    /// use axum::extract::ConnectInfo;
+    /// use tower_livereload::LiveReloadLayer;
+    ///
+    /// let layer = LiveReloadLayer::new().remote_addr(|extensions| {
+    ///     extensions
+    ///         .get::<ConnectInfo<SocketAddr>>()
+    ///         .map(|ConnectInfo(addr)| *addr)
+    /// });
+    /// ```
+    ///
+    /// [`axum`]: https://docs.rs/axum
+    pub fn remote_addr<F>(self, lookup: F) -> Self
+    where
+        F: Fn(&http::Extensions) -> Option<SocketAddr> + Send + Sync + 'static,
+    {
+        Self {
+            options: Options {
+                remote_addr: RemoteAddr(Arc::new(lookup)),
+                ..self.options
+            },
             ..self
         }
     }
@@ -237,56 +1057,296 @@ impl<S, ReqPred: Copy, ResPred: Copy> Layer<S> for LiveReloadLayer<ReqPred, ResP
             self.reloader.clone(),
             self.req_predicate,
             self.res_predicate,
-            self.reload_interval,
-            self.custom_prefix
-                .clone()
-                .unwrap_or_else(|| DEFAULT_PREFIX.to_owned()),
+            self.options.clone(),
         )
     }
 }
 
 type InnerService<S, ReqPred, ResPred> =
-    OverlayService<ReloadEventsBody, Infallible, InjectService<S, ReqPred, ResPred>>;
+    OverlayService<InternalBody, Infallible, InjectService<S, ReqPred, ResPred>>;
 
 /// Middleware to enable LiveReload functionality.
+///
+/// # Trigger route
+///
+/// Once enabled using [`LiveReloadLayer::trigger`], this middleware also
+/// serves a trigger route at `POST <prefix>/trigger`, with `<prefix>` being
+/// `/_tower-livereload` by default. Requests to this route send a reload to
+/// all connected clients, which is useful to trigger reloads from build tools:
+///
+/// ```sh
+/// curl -X POST localhost:3030/_tower-livereload/trigger
+/// ```
+///
+/// Other events can be sent by passing a command as the request body or the
+/// `command` query parameter, e.g. `css /static/app.css`. Commands use the
+/// same syntax as the control socket, which is described in the
+/// documentation of the `control` module.
+///
+/// Requests sent by browsers from other origins are rejected, so that
+/// websites cannot trigger reloads. The trigger route can be protected further
+/// using [`LiveReloadLayer::trigger_token`].
+///
+/// # Request bodies
+///
+/// As some internal routes read the request body, [`LiveReload`] requires
+/// request bodies to implement [`http_body::Body`]. This is the case for the
+/// request types of all common servers, such as `axum` and `hyper`. Note that
+/// this requirement is new in version 0.11, so services with other request
+/// types, e.g. `Request<()>`, can no longer be wrapped.
+///
+/// # Client events
+///
+/// Injected pages dispatch the following cancelable [`CustomEvent`]s on
+/// `window`, which allow applications to replace the built-in behavior by
+/// calling `preventDefault()`:
+///
+/// - `tower-livereload:message`: An event has been received from the server.
+///   The `detail` property contains its `type`, raw `data` and `id`.
+///   Cancelling skips the built-in handling of the event.
+/// - `tower-livereload:connected`: The page has connected to the event stream.
+///   The `detail` property contains whether the server has been `restarted`
+///   since the page was loaded. Cancelling skips the reload after a restart.
+/// - `tower-livereload:disconnected`: The page has lost its connection to the
+///   event stream. Cancelling stops the page from reconnecting.
+/// - `tower-livereload:before-reload`: The page is about to reload. The
+///   `detail` property contains the `reason` for the reload and the
+///   `strategy` used. Cancelling skips the reload, e.g. so that applications
+///   can fetch new data by themselves.
+///
+/// Custom events sent using [`ReloadEvent::Custom`] are dispatched as
+/// `tower-livereload:custom:<name>`, so that they cannot be confused with the
+/// events above.
+///
+/// [`CustomEvent`]: https://developer.mozilla.org/en-US/docs/Web/API/CustomEvent
 #[derive(Clone, Debug)]
 pub struct LiveReload<S, ReqPred = Always, ResPred = ContentTypeStartsWith<&'static str>> {
     service: InnerService<S, ReqPred, ResPred>,
 }
 
 impl<S, ReqPred, ResPred> LiveReload<S, ReqPred, ResPred> {
-    fn new<P: AsRef<str>>(
+    fn new(
         service: S,
         reloader: Reloader,
         req_predicate: ReqPred,
         res_predicate: ResPred,
-        reload_interval: Duration,
-        prefix: P,
+        options: Options,
     ) -> Self {
-        let event_stream_path = format!("{}/event-stream", prefix.as_ref());
+        let prefix = options.custom_prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+        let event_stream_path = format!("{}/event-stream", prefix);
+        let acknowledge_path = format!("{}/acknowledge", prefix);
+        let share_path = format!("{}/share", prefix);
+        let trigger_path = format!("{}/trigger", prefix);
+        let log_path = format!("{}/log", prefix);
+        let mut attributes = String::new();
+        if options.on_browser_log.is_some() {
+            attributes.push_str(&format!(r#" data-log="{}""#, log_path));
+            if options.capture_console {
+                attributes.push_str(" data-console");
+            }
+        }
+        if options.preserve_scroll {
+            attributes.push_str(" data-preserve-scroll");
+        }
+        if options.preserve_forms {
+            attributes.push_str(" data-preserve-forms");
+            if options.preserve_passwords {
+                attributes.push_str(" data-preserve-passwords");
+            }
+        }
+        if options.defer_hidden_reloads {
+            attributes.push_str(" data-defer-hidden");
+        }
+        if options.share_connection {
+            attributes.push_str(&format!(r#" data-share="{}""#, share_path));
+        }
+        if options.reload_strategy == ReloadStrategy::Morph {
+            attributes.push_str(&format!(r#" data-morph="{}""#, NO_INJECT_HEADER));
+        }
         let inject = InjectService::new(
             service,
             format!(
-                r#"<script data-event-stream="{path}">{code}</script>"#,
+                r#" data-event-stream="{path}" data-acknowledge="{ack_path}" data-channels="{channels}" data-instance="{instance}"{attributes}>{code}</script>"#,
                 path = event_stream_path,
+                instance = instance_id(),
+                ack_path = acknowledge_path,
+                channels = escape_attribute(&options.channels.join(" ")),
+                attributes = attributes,
                 code = include_str!("../assets/sse_reload.js"),
             )
             .into(),
+            reloader.history.clone(),
             req_predicate,
             res_predicate,
         );
         let overlay = OverlayService::new(inject, move |parts| {
             if parts.uri.path() == event_stream_path {
-                return Some(
+                // Browsers send the header when reconnecting, while the query
+                // parameter is used by pages connecting for the first time.
+                let last_event_id = parts
+                    .headers
+                    .get("last-event-id")
+                    .and_then(|value| value.to_str().ok().map(ToOwned::to_owned))
+                    .or_else(|| query::get(&parts.uri, "last-event-id"))
+                    .and_then(|id| id.parse().ok());
+                let client = Client::from_request(parts, &options.remote_addr);
+                let registration = reloader.registry.register(client.clone());
+
+                return Some(Alternative::Ready(
                     Response::builder()
                         .status(StatusCode::OK)
                         .header(header::CONTENT_TYPE, "text/event-stream")
-                        .body(ReloadEventsBody::new(
-                            reloader.sender.clone(),
-                            reload_interval,
-                        ))
+                        .body(InternalBody::Events(Box::new(ReloadEventsBody::new(
+                            reloader.history.clone(),
+                            client,
+                            registration,
+                            query::get(&parts.uri, "shared").is_some(),
+                            last_event_id,
+                            options.reload_interval,
+                            options.debounce,
+                        ))))
                         .map_err(|_| unreachable!()),
-                );
+                ));
+            }
+
+            if parts.uri.path() == acknowledge_path {
+                if parts.method != Method::POST {
+                    return Some(Alternative::Ready(method_not_allowed()));
+                }
+
+                if !same_origin(parts) {
+                    return Some(Alternative::Ready(cross_origin()));
+                }
+
+                let id = query::get(&parts.uri, "id").and_then(|id| id.parse().ok());
+                if let (Some(id), Some(token)) = (id, query::get(&parts.uri, "client")) {
+                    reloader.acks.acknowledge(id, &token);
+                }
+
+                return Some(Alternative::Ready(text(StatusCode::NO_CONTENT, None)));
+            }
+
+            if parts.uri.path() == share_path {
+                if parts.method != Method::POST {
+                    return Some(Alternative::Ready(method_not_allowed()));
+                }
+
+                if !same_origin(parts) {
+                    return Some(Alternative::Ready(cross_origin()));
+                }
+
+                let stream = query::get(&parts.uri, "stream").and_then(|id| id.parse().ok());
+                let client = Client::from_request(parts, &options.remote_addr);
+                match (stream, client.token.clone()) {
+                    (Some(stream), Some(token)) if query::get(&parts.uri, "leave").is_some() => {
+                        reloader.registry.unfollow(stream, &token);
+                    }
+                    (Some(stream), Some(_)) => reloader.registry.follow(stream, client),
+                    _ => {
+                        return Some(Alternative::Ready(text(
+                            StatusCode::BAD_REQUEST,
+                            Some("error: missing stream or client".to_owned()),
+                        )))
+                    }
+                }
+
+                return Some(Alternative::Ready(text(StatusCode::NO_CONTENT, None)));
+            }
+
+            if parts.uri.path() == trigger_path && options.trigger {
+                if parts.method != Method::POST {
+                    return Some(Alternative::Ready(method_not_allowed()));
+                }
+
+                if !same_origin(parts) {
+                    return Some(Alternative::Ready(cross_origin()));
+                }
+
+                if let Some(token) = &options.trigger_token {
+                    let bearer = parts
+                        .headers
+                        .get(header::AUTHORIZATION)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.strip_prefix("Bearer "))
+                        .map(ToOwned::to_owned);
+                    if bearer.or_else(|| query::get(&parts.uri, "token")).as_ref() != Some(token) {
+                        return Some(Alternative::Ready(text(
+                            StatusCode::UNAUTHORIZED,
+                            Some("error: invalid token".to_owned()),
+                        )));
+                    }
+                }
+
+                let reloader = reloader.clone();
+                let command = query::get(&parts.uri, "command");
+                return Some(Alternative::WithBody(Box::new(move |body| {
+                    let Some(body) = body.and_then(|body| String::from_utf8(body.to_vec()).ok())
+                    else {
+                        return text(
+                            StatusCode::BAD_REQUEST,
+                            Some("error: unable to read request body".to_owned()),
+                        );
+                    };
+
+                    let lines = command
+                        .iter()
+                        .map(String::as_str)
+                        .chain(body.lines())
+                        .filter(|line| !line.trim().is_empty())
+                        .collect::<Vec<_>>();
+                    let commands = if lines.is_empty() {
+                        Ok(vec![Command::Reload(None)])
+                    } else {
+                        lines.into_iter().map(Command::parse).collect()
+                    };
+
+                    match commands {
+                        Ok(commands) => {
+                            let responses = commands
+                                .into_iter()
+                                .map(|command| command.execute(&reloader))
+                                .collect::<Vec<_>>();
+                            text(StatusCode::OK, Some(responses.join("\n")))
+                        }
+                        Err(error) => {
+                            text(StatusCode::BAD_REQUEST, Some(format!("error: {}", error)))
+                        }
+                    }
+                })));
+            }
+
+            if parts.uri.path() == log_path {
+                let handler = options.on_browser_log.clone()?;
+                if parts.method != Method::POST {
+                    return Some(Alternative::Ready(method_not_allowed()));
+                }
+
+                // Browsers send simple requests to other origins without
+                // asking, so websites could otherwise report fake errors.
+                if !same_origin(parts) {
+                    return Some(Alternative::Ready(cross_origin()));
+                }
+
+                let user_agent = parts
+                    .headers
+                    .get(header::USER_AGENT)
+                    .and_then(|value| value.to_str().ok().map(ToOwned::to_owned));
+                let remote_addr = (options.remote_addr.0)(&parts.extensions);
+                return Some(Alternative::WithBody(Box::new(move |body| {
+                    let log = body
+                        .and_then(|body| String::from_utf8(body.to_vec()).ok())
+                        .and_then(|body| BrowserLog::parse(&body, user_agent, remote_addr));
+                    match log {
+                        Some(log) => {
+                            (handler.0)(log);
+                            text(StatusCode::NO_CONTENT, None)
+                        }
+                        None => text(
+                            StatusCode::BAD_REQUEST,
+                            Some("error: invalid log message".to_owned()),
+                        ),
+                    }
+                })));
             }
 
             None
@@ -300,6 +1360,7 @@ impl<ReqBody, ResBody, S, ReqPred, ResPred> Service<Request<ReqBody>>
     for LiveReload<S, ReqPred, ResPred>
 where
     S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: http_body::Body,
     ResBody: http_body::Body,
     ReqPred: Predicate<Request<ReqBody>>,
     ResPred: Predicate<Response<ResBody>>,
@@ -319,3 +1380,80 @@ where
         self.service.call(req)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribed_channels() {
+        let client = Client::test("/", &["wasm", "templates"]);
+        assert!(client.subscribed(None));
+        assert!(client.subscribed(Some("wasm")));
+        assert!(!client.subscribed(Some("styles")));
+
+        let client = Client::test("/", &[]);
+        assert!(client.subscribed(None));
+        assert!(!client.subscribed(Some("wasm")));
+    }
+
+    #[test]
+    fn same_origin_requests() {
+        let parts = |uri: &str, headers: &[(header::HeaderName, &str)]| {
+            let mut request = Request::post(uri);
+            for (name, value) in headers {
+                request = request.header(name, *value);
+            }
+            request.body(()).unwrap().into_parts().0
+        };
+
+        let host = || (header::HOST, "localhost:3030");
+        assert!(same_origin(&parts("/", &[host()])));
+        assert!(same_origin(&parts(
+            "/",
+            &[host(), (header::ORIGIN, "http://localhost:3030")]
+        )));
+        assert!(same_origin(&parts(
+            "https://localhost:3030/",
+            &[(header::ORIGIN, "https://localhost:3030")]
+        )));
+        assert!(!same_origin(&parts(
+            "/",
+            &[host(), (header::ORIGIN, "https://example.com")]
+        )));
+        assert!(!same_origin(&parts(
+            "/",
+            &[host(), (header::ORIGIN, "null")]
+        )));
+        assert!(!same_origin(&parts(
+            "/",
+            &[(header::ORIGIN, "http://localhost:3030")]
+        )));
+    }
+
+    #[test]
+    fn glob_match_exact() {
+        assert!(glob_match("/docs", "/docs"));
+        assert!(!glob_match("/docs", "/docs/"));
+        assert!(!glob_match("/docs", "/doc"));
+        assert!(glob_match("", ""));
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("/docs/*", "/docs/"));
+        assert!(glob_match("/docs/*", "/docs/guide/install"));
+        assert!(!glob_match("/docs/*", "/blog/"));
+        assert!(glob_match("*", "/"));
+        assert!(glob_match("/*.html", "/index.html"));
+        assert!(!glob_match("/*.html", "/index.htm"));
+        assert!(glob_match("/*/edit*", "/posts/edit/1"));
+        assert!(glob_match("/**", "/a"));
+    }
+
+    #[test]
+    fn glob_match_unicode() {
+        assert!(glob_match("/caf*/menu", "/café/menu"));
+        assert!(!glob_match("/caf*/menu", "/café/menü"));
+    }
+}
@@ -1,19 +1,36 @@
-use std::{convert::Infallible, future::Future, sync::Arc, task::Poll};
-
-use bytes::Buf;
+use std::{
+    convert::Infallible,
+    future::Future,
+    sync::Arc,
+    task::{ready, Poll},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use http::{request::Parts, Request, Response};
 use http_body::{Body, Frame};
 use tower::Service;
 
+/// Maximum size of request bodies that are read for alternative responses.
+const BODY_LIMIT: usize = 1024 * 1024;
+
+pub type Handler<B, E> = Box<dyn FnOnce(Option<Bytes>) -> Result<Response<B>, E> + Send>;
+
+pub enum Alternative<B, E> {
+    Ready(Result<Response<B>, E>),
+    /// Respond once the request body has been read, or with `None` if it
+    /// could not be read.
+    WithBody(Handler<B, E>),
+}
+
 pub struct OverlayService<B, E, S> {
-    alternative: Arc<dyn Fn(&Parts) -> Option<Result<Response<B>, E>> + Send + Sync>,
+    alternative: Arc<dyn Fn(&Parts) -> Option<Alternative<B, E>> + Send + Sync>,
     service: S,
 }
 
 impl<B, E, S> OverlayService<B, E, S> {
     pub fn new(
         service: S,
-        alternative_fn: impl Fn(&Parts) -> Option<Result<Response<B>, E>> + Send + Sync + 'static,
+        alternative_fn: impl Fn(&Parts) -> Option<Alternative<B, E>> + Send + Sync + 'static,
     ) -> Self {
         Self {
             alternative: Arc::new(alternative_fn),
@@ -44,10 +61,11 @@ impl<S, E, ReqBody, ResBody, ResBodyNew> Service<Request<ReqBody>>
     for OverlayService<ResBodyNew, E, S>
 where
     S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: Body,
 {
     type Response = Response<OverlayBody<ResBodyNew, ResBody>>;
     type Error = OverlayError<E, S::Error>;
-    type Future = OverlayFuture<ResBodyNew, E, S::Future>;
+    type Future = OverlayFuture<ResBodyNew, E, S::Future, ReqBody>;
 
     fn poll_ready(
         &mut self,
@@ -58,21 +76,25 @@ where
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
         let (parts, body) = req.into_parts();
-        if let Some(result) = self.alternative.clone()(&parts) {
-            OverlayFuture::Alternative {
+        match self.alternative.clone()(&parts) {
+            Some(Alternative::Ready(result)) => OverlayFuture::Alternative {
                 alternative: Some(result),
-            }
-        } else {
-            OverlayFuture::Inner {
+            },
+            Some(Alternative::WithBody(handler)) => OverlayFuture::Collect {
+                body,
+                buffer: BytesMut::new(),
+                handler: Some(handler),
+            },
+            None => OverlayFuture::Inner {
                 inner: self.service.call(Request::from_parts(parts, body)),
-            }
+            },
         }
     }
 }
 
 pin_project_lite::pin_project! {
     #[project = OverlayFutureProj]
-    pub enum OverlayFuture<B, E, F> {
+    pub enum OverlayFuture<B, E, F, RB> {
         Inner {
             #[pin]
             inner: F
@@ -80,12 +102,19 @@ pin_project_lite::pin_project! {
         Alternative {
             alternative: Option<Result<Response<B>, E>>
         },
+        Collect {
+            #[pin]
+            body: RB,
+            buffer: BytesMut,
+            handler: Option<Handler<B, E>>,
+        },
     }
 }
 
-impl<B, E, PB, PE, F> Future for OverlayFuture<B, E, F>
+impl<B, E, PB, PE, F, RB> Future for OverlayFuture<B, E, F, RB>
 where
     F: Future<Output = Result<Response<PB>, PE>>,
+    RB: Body,
 {
     type Output = Result<Response<OverlayBody<B, PB>>, OverlayError<E, PE>>;
 
@@ -106,6 +135,33 @@ where
                     })
                     .unwrap_or_else(|| unreachable!()),
             ),
+            OverlayFutureProj::Collect {
+                mut body,
+                buffer,
+                handler,
+            } => {
+                let data = loop {
+                    match ready!(body.as_mut().poll_frame(cx)) {
+                        Some(Ok(frame)) => {
+                            if let Ok(data) = frame.into_data() {
+                                if buffer.len() + data.remaining() > BODY_LIMIT {
+                                    break None;
+                                }
+                                buffer.put(data);
+                            }
+                        }
+                        Some(Err(_)) => break None,
+                        None => break Some(std::mem::take(buffer).freeze()),
+                    }
+                };
+
+                let handler = handler.take().unwrap_or_else(|| unreachable!());
+                Poll::Ready(
+                    handler(data)
+                        .map(|ok| ok.map(|left| OverlayBody::Left { left }))
+                        .map_err(OverlayError::Left),
+                )
+            }
         }
     }
 }
@@ -0,0 +1,81 @@
+use http::Uri;
+
+/// Return the percent-decoded value of the first query parameter with the
+/// given name.
+pub fn get(uri: &Uri, name: &str) -> Option<String> {
+    get_form(uri.query()?, name)
+}
+
+/// Return the percent-decoded value of the first field with the given name in
+/// a `application/x-www-form-urlencoded` string.
+pub fn get_form(form: &str, name: &str) -> Option<String> {
+    form.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (decode(key) == name).then(|| decode(value))
+    })
+}
+
+fn decode(input: &str) -> String {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut iter = input.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = iter.clone().take(2).collect::<Vec<_>>();
+                match std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|hex| hex.len() == 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(decoded) => {
+                        bytes.push(decoded);
+                        iter.nth(1);
+                    }
+                    None => bytes.push(b'%'),
+                }
+            }
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_escapes() {
+        assert_eq!(decode("a+b%20c"), "a b c");
+        assert_eq!(decode("%2Fstatic%2fapp.css"), "/static/app.css");
+        assert_eq!(decode("%C3%A9t%C3%A9"), "été");
+    }
+
+    #[test]
+    fn decode_invalid_escapes() {
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz"), "%zz");
+        assert_eq!(decode("%4"), "%4");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn get_first_value() {
+        let uri: Uri = "/event-stream?path=%2Fdocs%2F&flag&path=/other"
+            .parse()
+            .unwrap();
+        assert_eq!(get(&uri, "path").as_deref(), Some("/docs/"));
+        assert_eq!(get(&uri, "flag").as_deref(), Some(""));
+        assert_eq!(get(&uri, "missing"), None);
+        assert_eq!(get(&"/event-stream".parse().unwrap(), "path"), None);
+    }
+
+    #[test]
+    fn get_form_field() {
+        let form = "kind=console&message=hello+world%21";
+        assert_eq!(get_form(form, "message").as_deref(), Some("hello world!"));
+        assert_eq!(get_form(form, "kind").as_deref(), Some("console"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::Client;
+
+/// Registry of clients that are currently connected to an event stream.
+#[derive(Debug, Default)]
+pub struct Registry {
+    clients: Mutex<Clients>,
+}
+
+#[derive(Debug, Default)]
+struct Clients {
+    next_id: u64,
+    entries: BTreeMap<u64, Client>,
+    /// Clients that share the event stream of another client, keyed by the id
+    /// of its registration and their token.
+    followers: BTreeMap<u64, BTreeMap<String, Client>>,
+}
+
+impl Registry {
+    /// Add the given client to the registry until the returned
+    /// [`Registration`] is dropped.
+    pub fn register(self: &Arc<Self>, client: Client) -> Registration {
+        let mut clients = self.clients.lock().unwrap();
+        let id = clients.next_id;
+        clients.next_id += 1;
+        clients.entries.insert(id, client);
+
+        Registration {
+            registry: self.clone(),
+            id,
+        }
+    }
+
+    /// Add the given client as sharing the event stream of the registration
+    /// with the given id, until that registration is dropped.
+    ///
+    /// Clients without a token and unknown registrations are ignored.
+    pub fn follow(&self, stream: u64, client: Client) {
+        let mut clients = self.clients.lock().unwrap();
+        let Some(token) = client.token.clone() else {
+            return;
+        };
+        if clients.entries.contains_key(&stream) {
+            clients
+                .followers
+                .entry(stream)
+                .or_default()
+                .insert(token, client);
+        }
+    }
+
+    /// Remove the client with the given token from the clients sharing the
+    /// event stream of the registration with the given id.
+    pub fn unfollow(&self, stream: u64, token: &str) {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(followers) = clients.followers.get_mut(&stream) {
+            followers.remove(token);
+        }
+    }
+
+    pub fn clients(&self) -> Vec<Client> {
+        let clients = self.clients.lock().unwrap();
+        clients
+            .entries
+            .values()
+            .chain(clients.followers.values().flat_map(BTreeMap::values))
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        let clients = self.clients.lock().unwrap();
+        clients.entries.len() + clients.followers.values().map(BTreeMap::len).sum::<usize>()
+    }
+}
+
+pub struct Registration {
+    registry: Arc<Registry>,
+    id: u64,
+}
+
+impl Registration {
+    /// Return the id of the registration, which other clients use to share
+    /// its event stream.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Return the clients that share the event stream of this registration.
+    pub fn followers(&self) -> Vec<Client> {
+        self.registry
+            .clients
+            .lock()
+            .unwrap()
+            .followers
+            .get(&self.id)
+            .map(|followers| followers.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut clients = self.registry.clients.lock().unwrap();
+        clients.entries.remove(&self.id);
+        clients.followers.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(path: &str, token: &str) -> Client {
+        Client {
+            token: Some(token.to_owned()),
+            ..Client::test(path, &[])
+        }
+    }
+
+    #[test]
+    fn followers_share_registration() {
+        let registry = Arc::new(Registry::default());
+        let registration = registry.register(client("/", "leader"));
+        registry.follow(registration.id(), client("/docs/", "follower"));
+        registry.follow(registration.id() + 1, client("/blog/", "unknown"));
+        registry.follow(registration.id(), Client::test("/blog/", &[]));
+
+        assert_eq!(registry.len(), 2);
+        let followers = registration.followers();
+        assert_eq!(followers.len(), 1);
+        assert_eq!(followers[0].path(), "/docs/");
+
+        registry.unfollow(registration.id(), "follower");
+        assert_eq!(registry.len(), 1);
+
+        registry.follow(registration.id(), client("/docs/", "follower"));
+        drop(registration);
+        assert_eq!(registry.len(), 0);
+        assert!(registry.clients().is_empty());
+    }
+}
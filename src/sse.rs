@@ -1,30 +1,204 @@
-use std::{convert::Infallible, future::Future, pin::Pin, sync::Arc, task::Poll, time::Duration};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    convert::Infallible,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll},
+    time::Duration,
+};
 
+use bytes::{Bytes, BytesMut};
 use http_body::Frame;
-use tokio::sync::{futures::OwnedNotified, Notify};
+use tokio::{
+    sync::futures::OwnedNotified,
+    time::{Instant, Sleep},
+};
+
+use crate::{
+    event::{BuildStatus, Diagnostic, Payload, ReloadEvent},
+    history::{History, Message, Since},
+    registry::Registration,
+    Client,
+};
+
+/// Return an identifier that is unique to the current server process.
+///
+/// Clients use this identifier to distinguish server restarts, after which
+/// they have to reload, from mere network errors.
+pub fn instance_id() -> &'static str {
+    static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+    INSTANCE_ID.get_or_init(|| format!("{:016x}", RandomState::new().build_hasher().finish()))
+}
 
 pub struct ReloadEventsBody {
+    history: Arc<History>,
+    client: Client,
+    registration: Registration,
+    /// Whether the connection is shared with other clients, which register
+    /// themselves as followers of the registration.
+    shared: bool,
     state: State,
+    last_id: u64,
+    last_log: u64,
     retry_duration: Duration,
+    debounce: Option<Debounce>,
 }
 
 enum State {
-    Initial(Arc<Notify>),
+    Initial(Pin<Box<OwnedNotified>>),
     Pending(Pin<Box<OwnedNotified>>),
     Final,
 }
 
+struct Debounce {
+    delay: Duration,
+    seen: u64,
+    sleep: Option<Pin<Box<Sleep>>>,
+    /// Events that have been sent ahead of updates that are still held back.
+    early: Vec<u64>,
+}
+
 impl ReloadEventsBody {
-    pub fn new(receiver: Arc<Notify>, retry_duration: Duration) -> Self {
+    pub fn new(
+        history: Arc<History>,
+        client: Client,
+        registration: Registration,
+        shared: bool,
+        last_id: Option<u64>,
+        retry_duration: Duration,
+        debounce: Option<Duration>,
+    ) -> Self {
+        let notified = Box::pin(history.notified());
+        // Clients from previous server processes may know about newer
+        // generations than exist in the current process.
+        let last_id = last_id.map_or(history.latest(), |id| id.min(history.latest()));
+        let last_log = history.logged();
+
         Self {
-            state: State::Initial(receiver),
+            history,
+            client,
+            registration,
+            shared,
+            state: State::Initial(notified),
+            last_id,
+            last_log,
             retry_duration,
+            debounce: debounce.map(|delay| Debounce {
+                delay,
+                seen: last_id,
+                sleep: None,
+                early: Vec::new(),
+            }),
+        }
+    }
+
+    /// Check whether updates up to the given generation should be sent now.
+    fn settled(&mut self, latest: u64, cx: &mut Context<'_>) -> bool {
+        let Some(debounce) = &mut self.debounce else {
+            return true;
+        };
+
+        if latest > debounce.seen {
+            debounce.seen = latest;
+            let deadline = Instant::now() + debounce.delay;
+            match &mut debounce.sleep {
+                Some(sleep) => sleep.as_mut().reset(deadline),
+                None => debounce.sleep = Some(Box::pin(tokio::time::sleep_until(deadline))),
+            }
+        }
+
+        let ready = debounce
+            .sleep
+            .as_mut()
+            .is_some_and(|sleep| sleep.as_mut().poll(cx).is_ready());
+        if ready {
+            debounce.sleep = None;
+        }
+
+        ready
+    }
+
+    /// Return the client holding the connection, followed by all clients
+    /// sharing it.
+    fn clients(&self) -> Vec<Client> {
+        let mut clients = vec![self.client.clone()];
+        if self.shared {
+            clients.extend(self.registration.followers());
+        }
+
+        clients
+    }
+
+    /// Encode log messages sent since the last call, which are neither
+    /// debounced nor replayed after reconnects.
+    fn drain_logs(&mut self, frame: &mut BytesMut) {
+        let clients = self.clients();
+        let (logs, logged) = self
+            .history
+            .logs_since(self.last_log, &clients.iter().collect::<Vec<_>>());
+        self.last_log = logged;
+        for (_, log, to) in merge(logs, &clients, self.shared) {
+            // Repeat the id of the last sent event, so that clients resume
+            // from there after reconnecting.
+            frame.extend_from_slice(&encode(self.last_id, &log, to.as_deref()));
+        }
+    }
+
+    fn drain(&mut self, cx: &mut Context<'_>) -> Option<Bytes> {
+        let clients = self.clients();
+        let (events, latest) = match self
+            .history
+            .since(self.last_id, &clients.iter().collect::<Vec<_>>())
+        {
+            Since::Events(events, latest) => (events.into_iter().map(coalesce).collect(), latest),
+            // Events were dropped, so reload to be safe.
+            Since::Missed(latest) => (
+                vec![vec![(latest, Message::Event(ReloadEvent::Reload))]; clients.len()],
+                latest,
+            ),
+        };
+        let events = merge(events, &clients, self.shared);
+
+        let update = events
+            .iter()
+            .rev()
+            .find(|(_, message, _)| debounced(message))
+            .map(|(id, _, _)| *id);
+        let settled = update.map_or(true, |update| self.settled(update, cx));
+
+        let mut frame = BytesMut::new();
+        if settled {
+            self.last_id = latest;
+            let early = self
+                .debounce
+                .as_mut()
+                .map(|debounce| std::mem::take(&mut debounce.early))
+                .unwrap_or_default();
+            for (id, message, to) in events {
+                if !early.contains(&id) {
+                    frame.extend_from_slice(&encode(id, &message, to.as_deref()));
+                }
+            }
+        } else if let Some(debounce) = &mut self.debounce {
+            // Only updates are held back, other events are sent right away.
+            // They repeat the id of the last sent event, so that clients still
+            // receive the held back updates after reconnecting.
+            for (id, message, to) in events {
+                if !debounced(&message) && !debounce.early.contains(&id) {
+                    debounce.early.push(id);
+                    frame.extend_from_slice(&encode(self.last_id, &message, to.as_deref()));
+                }
+            }
         }
+
+        (!frame.is_empty()).then(|| frame.freeze())
     }
 }
 
 impl http_body::Body for ReloadEventsBody {
-    type Data = bytes::Bytes;
+    type Data = Bytes;
     type Error = Infallible;
 
     fn poll_frame(
@@ -32,25 +206,453 @@ impl http_body::Body for ReloadEventsBody {
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
         match std::mem::replace(&mut self.state, State::Final) {
-            State::Initial(notify) => {
-                self.state = State::Pending(Box::pin(notify.notified_owned()));
+            State::Initial(notified) => {
+                self.state = State::Pending(notified);
+
+                // Messages sent when connecting are addressed to all clients
+                // sharing the connection.
+                let to = self.shared.then_some("*");
+                let init = format!(
+                    r#"{{"instance":"{}","paused":{},"stream":{}}}"#,
+                    instance_id(),
+                    self.history.paused(),
+                    self.registration.id(),
+                );
+                let mut frame = BytesMut::from(&format_event(self.last_id, "init", &init, to)[..]);
+                frame.extend_from_slice(
+                    format!("retry: {}\n\n", self.retry_duration.as_millis()).as_bytes(),
+                );
+                // Let clients know about builds that are still in progress or
+                // have failed before they connected.
+                if let Some(status) = self.history.build() {
+                    let build = Message::Event(ReloadEvent::Build(status));
+                    frame.extend_from_slice(&encode(self.last_id, &build, to));
+                }
 
-                Poll::Ready(Some(Ok(Frame::data(bytes::Bytes::from_owner(format!(
-                    "event: init\ndata:\nretry: {}\n\n",
-                    self.retry_duration.as_millis()
-                ))))))
+                Poll::Ready(Some(Ok(Frame::data(frame.freeze()))))
             }
-            State::Pending(mut notified) => {
-                if notified.as_mut().poll(cx) == Poll::Pending {
+            State::Pending(mut notified) => loop {
+                let mut frame = BytesMut::new();
+                self.drain_logs(&mut frame);
+                if self.history.latest() > self.last_id {
+                    if let Some(events) = self.drain(cx) {
+                        frame.extend_from_slice(&events);
+                    }
+                }
+
+                if !frame.is_empty() {
+                    self.state = State::Pending(notified);
+                    return Poll::Ready(Some(Ok(Frame::data(frame.freeze()))));
+                }
+
+                if notified.as_mut().poll(cx).is_pending() {
                     self.state = State::Pending(notified);
                     return Poll::Pending;
                 }
+                notified = Box::pin(self.history.notified());
+            },
+            State::Final => Poll::Ready(None),
+        }
+    }
+}
+
+/// Check whether the given message is held back by
+/// [`LiveReloadLayer::debounce`](crate::LiveReloadLayer::debounce).
+fn debounced(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::Event(
+            ReloadEvent::Reload
+                | ReloadEvent::Stylesheet(_)
+                | ReloadEvent::Asset(_)
+                | ReloadEvent::Module(_)
+        )
+    )
+}
 
-                Poll::Ready(Some(Ok(Frame::data(bytes::Bytes::from_static(
-                    b"event: reload\ndata:\n\n",
-                )))))
+/// Merge the events addressed to each of the given clients, ordered by their
+/// id.
+///
+/// On shared connections, each event is tagged with the tokens of the clients
+/// it is addressed to, or `*` if it is addressed to all of them.
+fn merge(
+    events: Vec<Vec<(u64, Message)>>,
+    clients: &[Client],
+    shared: bool,
+) -> Vec<(u64, Message, Option<String>)> {
+    let mut merged: BTreeMap<u64, (Message, Vec<Option<&str>>)> = BTreeMap::new();
+    for (client, events) in clients.iter().zip(events) {
+        for (id, message) in events {
+            let (_, recipients) = merged.entry(id).or_insert_with(|| (message, Vec::new()));
+            recipients.push(client.token.as_deref());
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(id, (message, recipients))| {
+            let to = shared.then(|| {
+                if recipients.len() == clients.len() {
+                    "*".to_owned()
+                } else {
+                    recipients
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+            });
+            (id, message, to)
+        })
+        .collect()
+}
+
+/// Merge redundant events that were sent in quick succession.
+///
+/// Repeated stylesheet, asset and module updates are merged, and a full reload
+/// replaces every earlier update, as it makes them redundant. Only the most
+/// recent build status is kept. Custom events and internal messages are always
+/// kept as-is. Events keep their relative order, so that clients can resume
+/// from the id of the last event they have received.
+fn coalesce(messages: Vec<(u64, Message)>) -> Vec<(u64, Message)> {
+    let reload = messages
+        .iter()
+        .rev()
+        .find(|(_, message)| *message == Message::Event(ReloadEvent::Reload))
+        .map(|(id, _)| *id);
+
+    let mut coalesced: Vec<(u64, Message)> = Vec::with_capacity(messages.len());
+    for (id, message) in messages {
+        match message {
+            Message::Event(ReloadEvent::Custom { .. })
+            | Message::Event(ReloadEvent::Log(_))
+            | Message::Paused(_) => coalesced.push((id, message)),
+            Message::Event(ReloadEvent::Reload)
+            | Message::Event(ReloadEvent::Stylesheet(_))
+            | Message::Event(ReloadEvent::Asset(_))
+            | Message::Event(ReloadEvent::Module(_))
+                if reload.is_some_and(|reload| id < reload) => {}
+            Message::Event(ReloadEvent::Build(_)) => {
+                coalesced
+                    .retain(|(_, other)| !matches!(other, Message::Event(ReloadEvent::Build(_))));
+                coalesced.push((id, message));
             }
-            State::Final => Poll::Ready(None),
+            message => {
+                coalesced.retain(|(_, other)| *other != message);
+                coalesced.push((id, message));
+            }
+        }
+    }
+
+    coalesced
+}
+
+/// Encode the given message, prefixed with the clients it is addressed to on
+/// shared connections.
+fn encode(id: u64, message: &Message, to: Option<&str>) -> Bytes {
+    let (event, data) = match message {
+        Message::Event(ReloadEvent::Reload) => ("reload", String::new()),
+        Message::Event(ReloadEvent::Stylesheet(path)) => ("stylesheet", path.clone()),
+        Message::Event(ReloadEvent::Asset(path)) => ("asset", path.clone()),
+        Message::Event(ReloadEvent::Module(path)) => ("module", path.clone()),
+        Message::Event(ReloadEvent::Log(record)) => {
+            let mut data = String::from(r#"{"level":"#);
+            push_json_string(&mut data, &record.level.to_string());
+            data.push_str(r#","target":"#);
+            push_json_string(&mut data, &record.target);
+            data.push_str(r#","message":"#);
+            push_json_string(&mut data, &record.message);
+            data.push('}');
+            ("log", data)
         }
+        Message::Event(ReloadEvent::Custom { name, payload }) => {
+            let mut data = String::from(r#"{"name":"#);
+            push_json_string(&mut data, name);
+            data.push_str(r#","data":"#);
+            match payload {
+                Payload::Text(text) => push_json_string(&mut data, text),
+                #[cfg(feature = "json")]
+                Payload::Json(value) => data.push_str(&value.to_string()),
+            }
+            data.push('}');
+            ("custom", data)
+        }
+        Message::Event(ReloadEvent::Build(status)) => {
+            let data = match status {
+                BuildStatus::Started => r#"{"status":"started"}"#.to_owned(),
+                BuildStatus::Failed(message) => {
+                    let mut data = String::from(r#"{"status":"failed","message":"#);
+                    push_json_string(&mut data, message);
+                    data.push('}');
+                    data
+                }
+                BuildStatus::Diagnostics(diagnostics) => {
+                    let mut data = String::from(r#"{"status":"failed","diagnostics":["#);
+                    for (index, diagnostic) in diagnostics.iter().enumerate() {
+                        if index > 0 {
+                            data.push(',');
+                        }
+                        push_diagnostic(&mut data, diagnostic);
+                    }
+                    data.push_str("]}");
+                    data
+                }
+                BuildStatus::Succeeded => r#"{"status":"succeeded"}"#.to_owned(),
+            };
+            ("build", data)
+        }
+        Message::Paused(true) => ("paused", String::new()),
+        Message::Paused(false) => ("resumed", String::new()),
+    };
+
+    format_event(id, event, &data, to)
+}
+
+fn format_event(id: u64, event: &str, data: &str, to: Option<&str>) -> Bytes {
+    let mut message = format!("event: {}\nid: {}\n", event, id);
+    if let Some(to) = to {
+        message.push_str("data: ");
+        message.push_str(to);
+        message.push('\n');
+    }
+    for line in data.lines() {
+        message.push_str("data: ");
+        message.push_str(line);
+        message.push('\n');
+    }
+    if data.is_empty() {
+        message.push_str("data:\n");
+    }
+    message.push('\n');
+
+    Bytes::from_owner(message)
+}
+
+fn push_diagnostic(out: &mut String, diagnostic: &Diagnostic) {
+    out.push_str(r#"{"level":"#);
+    push_json_string(out, &diagnostic.level);
+    out.push_str(r#","message":"#);
+    push_json_string(out, &diagnostic.message);
+    if let Some(span) = &diagnostic.span {
+        out.push_str(r#","span":{"file":"#);
+        push_json_string(out, &span.file);
+        out.push_str(&format!(
+            r#","line_start":{},"line_end":{},"column_start":{},"column_end":{}}}"#,
+            span.line_start, span.line_end, span.column_start, span.column_end
+        ));
+    }
+    if let Some(rendered) = &diagnostic.rendered {
+        out.push_str(r#","rendered":"#);
+        push_json_string(out, rendered);
+    }
+    out.push('}');
+}
+
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\t' => out.push_str(r"\t"),
+            char if char.is_control() => out.push_str(&format!(r"\u{:04x}", char as u32)),
+            char => out.push(char),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use http_body::Body;
+    use tokio::time::{sleep, timeout};
+
+    use super::*;
+    use crate::registry::Registry;
+
+    fn body(history: &Arc<History>, debounce: Duration) -> ReloadEventsBody {
+        let client = Client::test("/", &[]);
+        let registration = Arc::new(Registry::default()).register(client.clone());
+        ReloadEventsBody::new(
+            history.clone(),
+            client,
+            registration,
+            false,
+            None,
+            Duration::from_secs(1),
+            Some(debounce),
+        )
+    }
+
+    async fn next(body: &mut ReloadEventsBody) -> Bytes {
+        poll_fn(|cx| Pin::new(&mut *body).poll_frame(cx))
+            .await
+            .unwrap()
+            .unwrap()
+            .into_data()
+            .unwrap()
+    }
+
+    fn event(event: ReloadEvent) -> Message {
+        Message::Event(event)
+    }
+
+    fn custom(name: &str) -> Message {
+        event(ReloadEvent::custom(name, Payload::text("")))
+    }
+
+    fn stylesheet(path: &str) -> Message {
+        event(ReloadEvent::Stylesheet(path.to_owned()))
+    }
+
+    #[test]
+    fn coalesce_keeps_ids_increasing() {
+        let coalesced = coalesce(vec![
+            (1, event(ReloadEvent::Reload)),
+            (2, custom("first")),
+            (3, Message::Paused(true)),
+            (4, event(ReloadEvent::Reload)),
+            (5, custom("second")),
+            (6, stylesheet("/app.css")),
+            (7, stylesheet("/app.css")),
+        ]);
+
+        let ids = coalesced.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3, 4, 5, 7]);
+    }
+
+    #[test]
+    fn coalesce_drops_updates_before_reload() {
+        let coalesced = coalesce(vec![
+            (1, stylesheet("/app.css")),
+            (2, event(ReloadEvent::Asset("/logo.png".to_owned()))),
+            (3, event(ReloadEvent::Reload)),
+            (4, stylesheet("/app.css")),
+        ]);
+
+        assert_eq!(
+            coalesced,
+            vec![(3, event(ReloadEvent::Reload)), (4, stylesheet("/app.css"))]
+        );
+    }
+
+    #[test]
+    fn coalesce_merges_updates() {
+        let coalesced = coalesce(vec![
+            (1, stylesheet("/app.css")),
+            (2, stylesheet("/theme.css")),
+            (3, event(ReloadEvent::Build(BuildStatus::Started))),
+            (4, stylesheet("/app.css")),
+            (5, event(ReloadEvent::Build(BuildStatus::Succeeded))),
+        ]);
+
+        assert_eq!(
+            coalesced,
+            vec![
+                (2, stylesheet("/theme.css")),
+                (4, stylesheet("/app.css")),
+                (5, event(ReloadEvent::Build(BuildStatus::Succeeded))),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounce_merges_bursts() {
+        let history = Arc::new(History::new(16));
+        let mut body = body(&history, Duration::from_millis(100));
+        next(&mut body).await;
+
+        let start = Instant::now();
+        history.push(ReloadEvent::Reload, None);
+        assert!(timeout(Duration::from_millis(60), next(&mut body))
+            .await
+            .is_err());
+
+        // Another reload within the delay pushes the deadline back.
+        history.push(ReloadEvent::Reload, None);
+        assert!(timeout(Duration::from_millis(60), next(&mut body))
+            .await
+            .is_err());
+
+        let frame = next(&mut body).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(160));
+        assert_eq!(frame, encode(2, &event(ReloadEvent::Reload), None));
+
+        // The next burst starts a new delay.
+        sleep(Duration::from_millis(500)).await;
+        let start = Instant::now();
+        history.push(ReloadEvent::Reload, None);
+        let frame = next(&mut body).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        assert_eq!(frame, encode(3, &event(ReloadEvent::Reload), None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounce_sends_other_events_right_away() {
+        let history = Arc::new(History::new(16));
+        let mut body = body(&history, Duration::from_millis(100));
+        next(&mut body).await;
+
+        let start = Instant::now();
+        history.push(ReloadEvent::Reload, None);
+        history.set_paused(true);
+        history.push(ReloadEvent::Build(BuildStatus::Started), None);
+
+        // Events sent ahead of the held back reload keep its id, so that the
+        // reload is not skipped after reconnecting.
+        let frame = next(&mut body).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        let mut expected = encode(0, &Message::Paused(true), None).to_vec();
+        expected.extend_from_slice(&encode(
+            0,
+            &event(ReloadEvent::Build(BuildStatus::Started)),
+            None,
+        ));
+        assert_eq!(frame, expected);
+
+        let frame = next(&mut body).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        assert_eq!(frame, encode(1, &event(ReloadEvent::Reload), None));
+    }
+
+    #[tokio::test]
+    async fn shared_streams_address_events() {
+        let history = Arc::new(History::new(16));
+        let registry = Arc::new(Registry::default());
+        let client = |path: &str, token: &str| Client {
+            token: Some(token.to_owned()),
+            ..Client::test(path, &[])
+        };
+        let registration = registry.register(client("/", "leader"));
+        registry.follow(registration.id(), client("/docs/", "follower"));
+        let mut body = ReloadEventsBody::new(
+            history.clone(),
+            client("/", "leader"),
+            registration,
+            true,
+            None,
+            Duration::from_secs(1),
+            None,
+        );
+
+        let init = next(&mut body).await;
+        assert!(init.starts_with(b"event: init\nid: 0\ndata: *\ndata: {"));
+
+        history.push(
+            ReloadEvent::Reload,
+            Some(Arc::new(|client: &Client| client.path() == "/docs/")),
+        );
+        history.push(ReloadEvent::Asset("/logo.png".to_owned()), None);
+        let mut expected = encode(1, &event(ReloadEvent::Reload), Some("follower")).to_vec();
+        expected.extend_from_slice(&encode(
+            2,
+            &event(ReloadEvent::Asset("/logo.png".to_owned())),
+            Some("*"),
+        ));
+        assert_eq!(next(&mut body).await, expected);
     }
 }